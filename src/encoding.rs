@@ -0,0 +1,625 @@
+use aes::cipher::{block_padding::Pkcs7, BlockDecryptMut, BlockEncryptMut, KeyIvInit};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use num::ToPrimitive;
+use rand::Rng;
+use sha2::Sha256;
+
+use crate::rsa::{PrivateKey, PublicKey, RsaInt};
+use crate::rsa_tools::{byte_length, from_bytes, to_bytes};
+
+//DER tags
+const INTEGER : u8 = 0x02;
+const BIT_STRING : u8 = 0x03;
+const OCTET_STRING : u8 = 0x04;
+const NULL : u8 = 0x05;
+const OBJECT_IDENTIFIER : u8 = 0x06;
+const SEQUENCE : u8 = 0x30;
+
+//DER encoding of the rsaEncryption OID 1.2.840.113549.1.1.1
+const RSA_ENCRYPTION_OID : [u8; 9] = [0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01];
+//DER encoding of the PBES2 OIDs (RFC 8018)
+const PBES2_OID : [u8; 9] = [0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x05, 0x0d];
+const PBKDF2_OID : [u8; 9] = [0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x05, 0x0c];
+const HMAC_SHA256_OID : [u8; 8] = [0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x02, 0x09];
+const AES256_CBC_OID : [u8; 9] = [0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x01, 0x2a];
+
+const ENCRYPTED_LABEL : &str = "ENCRYPTED PRIVATE KEY";
+const PBKDF2_ITERATIONS : u32 = 100_000;
+const SALT_LEN : usize = 16;
+const AES_KEY_LEN : usize = 32;
+const AES_BLOCK_LEN : usize = 16;
+
+type Aes256CbcEnc = cbc::Encryptor<aes::Aes256>;
+type Aes256CbcDec = cbc::Decryptor<aes::Aes256>;
+
+/**
+ * Errors returned when a key is decoded.
+ * It converts into RsaError::Encoding.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EncodingError {
+    /** The DER structure is malformed */
+    InvalidDer,
+    /** The PEM armor or its base64 is malformed */
+    InvalidPem,
+    /** The PEM label does not match the expected key */
+    UnexpectedLabel(String),
+    /** The key is not a RSA key */
+    UnsupportedAlgorithm,
+    /** An integer does not fit in the integer type */
+    IntegerTooBig,
+    /** The components of the key are inconsistent */
+    InvalidKey,
+    /** The private key has no p and q to export */
+    MissingCrtComponents,
+    /** The key is encrypted with an unknown cipher or KDF */
+    UnsupportedCipher(String),
+    /** The key is encrypted and no passphrase was given */
+    MissingPassphrase,
+    /** The key can not be decrypted with the passphrase */
+    WrongPassphrase,
+    /** The JSON document is malformed */
+    InvalidJson(String),
+    /** The key file can not be read or written */
+    Io(String),
+}
+
+impl std::fmt::Display for EncodingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EncodingError::InvalidDer => write!(f, "invalid DER structure"),
+            EncodingError::InvalidPem => write!(f, "invalid PEM armor"),
+            EncodingError::UnexpectedLabel(label) => write!(f, "unexpected PEM label {}", label),
+            EncodingError::UnsupportedAlgorithm => write!(f, "not a RSA key"),
+            EncodingError::IntegerTooBig => write!(f, "integer too big for the integer type"),
+            EncodingError::InvalidKey => write!(f, "inconsistent key components"),
+            EncodingError::MissingCrtComponents => write!(f, "the private key has no prime factors"),
+            EncodingError::UnsupportedCipher(cipher) => write!(f, "unsupported cipher {}", cipher),
+            EncodingError::MissingPassphrase => write!(f, "the key is encrypted, a passphrase is needed"),
+            EncodingError::WrongPassphrase => write!(f, "wrong passphrase"),
+            EncodingError::InvalidJson(e) => write!(f, "invalid JSON : {}", e),
+            EncodingError::Io(e) => write!(f, "key file error : {}", e),
+        }
+    }
+}
+
+impl std::error::Error for EncodingError {}
+
+/**
+ * ASN.1 structures available for the keys.
+ * Pkcs1 is RSAPublicKey / RSAPrivateKey (RFC 8017),
+ * Pkcs8 is SubjectPublicKeyInfo (RFC 5280) / PrivateKeyInfo (RFC 5208).
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyFormat {
+    /** PKCS#1 RSAPublicKey / RSAPrivateKey */
+    Pkcs1,
+    /** PKCS#8 PrivateKeyInfo, or SubjectPublicKeyInfo for a public key */
+    Pkcs8,
+}
+
+impl KeyFormat {
+    fn public_label(&self) -> &'static str {
+        match self {
+            KeyFormat::Pkcs1 => "RSA PUBLIC KEY",
+            KeyFormat::Pkcs8 => "PUBLIC KEY",
+        }
+    }
+
+    fn private_label(&self) -> &'static str {
+        match self {
+            KeyFormat::Pkcs1 => "RSA PRIVATE KEY",
+            KeyFormat::Pkcs8 => "PRIVATE KEY",
+        }
+    }
+}
+
+/**
+ * DER encoding of a public key.
+ */
+pub fn public_key_to_der<T>(key: &PublicKey<T>, format: KeyFormat) -> Vec<u8>
+where T: RsaInt
+{
+    let rsa_public_key: Vec<u8> = der_sequence(&[der_integer(key.n()), der_integer(key.e())]);
+    match format {
+        KeyFormat::Pkcs1 => rsa_public_key,
+        KeyFormat::Pkcs8 => {
+            let mut bit_string: Vec<u8> = vec![0x00];     //no unused bits
+            bit_string.extend_from_slice(&rsa_public_key);
+            der_sequence(&[rsa_algorithm_identifier_(), der_tlv(BIT_STRING, &bit_string)])
+        }
+    }
+}
+
+/**
+ * DER decoding of a public key.
+ */
+pub fn public_key_from_der<T>(der: &[u8], format: KeyFormat) -> Result<PublicKey<T>, EncodingError>
+where T: RsaInt
+{
+    let rsa_public_key: Vec<u8> = match format {
+        KeyFormat::Pkcs1 => der.to_vec(),
+        KeyFormat::Pkcs8 => {
+            let mut spki: DerReader = DerReader::new(DerReader::new(der).read_last(SEQUENCE)?);
+            read_rsa_algorithm_identifier_(&mut spki)?;
+            let bit_string: &[u8] = spki.read_last(BIT_STRING)?;
+            match bit_string.split_first() {
+                Some((0x00, rsa_public_key)) => rsa_public_key.to_vec(),
+                _ => return Err(EncodingError::InvalidDer),
+            }
+        }
+    };
+
+    let mut sequence: DerReader = DerReader::new(DerReader::new(&rsa_public_key).read_last(SEQUENCE)?);
+    let n: T = sequence.read_integer()?;
+    let e: T = sequence.read_integer()?;
+    sequence.finish()?;
+    let public: PublicKey<T> = PublicKey::construct(e, n);
+    if !public.is_valid() {
+        return Err(EncodingError::InvalidKey);
+    }
+    Ok(public)
+}
+
+/**
+ * DER encoding of a private key.
+ * The prime factors are required by both formats.
+ */
+pub fn private_key_to_der<T>(key: &PrivateKey<T>, format: KeyFormat) -> Result<Vec<u8>, EncodingError>
+where T: RsaInt
+{
+    let (p, q, dp, dq, qinv) = match (key.p(), key.q(), key.dp(), key.dq(), key.qinv()) {
+        (Some(p), Some(q), Some(dp), Some(dq), Some(qinv)) => (p, q, dp, dq, qinv),
+        _ => return Err(EncodingError::MissingCrtComponents),
+    };
+    let rsa_private_key: Vec<u8> = der_sequence(&[
+        der_integer(T::zero()),     //version two-prime
+        der_integer(key.n()),
+        der_integer(key.e()),
+        der_integer(key.d()),
+        der_integer(p),
+        der_integer(q),
+        der_integer(dp),
+        der_integer(dq),
+        der_integer(qinv),
+    ]);
+    match format {
+        KeyFormat::Pkcs1 => Ok(rsa_private_key),
+        KeyFormat::Pkcs8 => Ok(der_sequence(&[
+            der_integer(T::zero()),
+            rsa_algorithm_identifier_(),
+            der_tlv(OCTET_STRING, &rsa_private_key),
+        ])),
+    }
+}
+
+/**
+ * DER decoding of a private key.
+ * The CRT components are recomputed from p, q, e and d.
+ */
+pub fn private_key_from_der<T>(der: &[u8], format: KeyFormat) -> Result<PrivateKey<T>, EncodingError>
+where T: RsaInt
+{
+    let rsa_private_key: Vec<u8> = match format {
+        KeyFormat::Pkcs1 => der.to_vec(),
+        KeyFormat::Pkcs8 => {
+            let mut info: DerReader = DerReader::new(DerReader::new(der).read_last(SEQUENCE)?);
+            if info.read_integer::<T>()? != T::zero() {
+                return Err(EncodingError::InvalidDer);
+            }
+            read_rsa_algorithm_identifier_(&mut info)?;
+            info.read(OCTET_STRING)?.to_vec()       //optional attributes are ignored
+        }
+    };
+
+    let mut sequence: DerReader = DerReader::new(DerReader::new(&rsa_private_key).read_last(SEQUENCE)?);
+    if sequence.read_integer::<T>()? != T::zero() {
+        return Err(EncodingError::UnsupportedAlgorithm);    //multi-prime keys
+    }
+    let n: T = sequence.read_integer()?;
+    let e: T = sequence.read_integer()?;
+    let d: T = sequence.read_integer()?;
+    let p: T = sequence.read_integer()?;
+    let q: T = sequence.read_integer()?;
+    for _ in 0..3 {
+        sequence.read_integer::<T>()?;      //dP, dQ and qInv are recomputed
+    }
+    sequence.finish()?;
+
+    if p <= T::one() || q <= T::one() || p == q || p.checked_mul(&q).as_ref() != Some(&n) || !PublicKey::construct(e.clone(), n).is_valid() {
+        return Err(EncodingError::InvalidKey);
+    }
+    PrivateKey::construct_crt(p, q, e, d).map_err(|_| EncodingError::InvalidKey)
+}
+
+/**
+ * PEM encoding of a public key ("RSA PUBLIC KEY" or "PUBLIC KEY").
+ */
+pub fn public_key_to_pem<T>(key: &PublicKey<T>, format: KeyFormat) -> String
+where T: RsaInt
+{
+    pem_encode(format.public_label(), &public_key_to_der(key, format))
+}
+
+/**
+ * PEM decoding of a public key, the format is found from the label.
+ */
+pub fn public_key_from_pem<T>(pem: &str) -> Result<PublicKey<T>, EncodingError>
+where T: RsaInt
+{
+    let (label, der) = pem_decode(pem)?;
+    for format in [KeyFormat::Pkcs1, KeyFormat::Pkcs8] {
+        if label == format.public_label() {
+            return public_key_from_der(&der, format);
+        }
+    }
+    Err(EncodingError::UnexpectedLabel(label))
+}
+
+/**
+ * PEM encoding of a private key ("RSA PRIVATE KEY" or "PRIVATE KEY").
+ */
+pub fn private_key_to_pem<T>(key: &PrivateKey<T>, format: KeyFormat) -> Result<String, EncodingError>
+where T: RsaInt
+{
+    Ok(pem_encode(format.private_label(), &private_key_to_der(key, format)?))
+}
+
+/**
+ * PEM decoding of a private key, the format is found from the label.
+ */
+pub fn private_key_from_pem<T>(pem: &str) -> Result<PrivateKey<T>, EncodingError>
+where T: RsaInt
+{
+    let (label, der) = pem_decode(pem)?;
+    for format in [KeyFormat::Pkcs1, KeyFormat::Pkcs8] {
+        if label == format.private_label() {
+            return private_key_from_der(&der, format);
+        }
+    }
+    Err(EncodingError::UnexpectedLabel(label))
+}
+
+/**
+ * PKCS#8 EncryptedPrivateKeyInfo of a private key (RFC 5958).
+ * The PrivateKeyInfo is encrypted with PBES2 : a key derived from the passphrase
+ * with PBKDF2-HMAC-SHA256 and AES-256-CBC, as openssl pkcs8 -topk8 does.
+ */
+pub fn private_key_to_encrypted_der<T>(key: &PrivateKey<T>, passphrase: &str) -> Result<Vec<u8>, EncodingError>
+where T: RsaInt
+{
+    let info: Vec<u8> = private_key_to_der(key, KeyFormat::Pkcs8)?;
+    let mut rng = rand::thread_rng();
+    let mut salt: [u8; SALT_LEN] = [0; SALT_LEN];
+    let mut iv: [u8; AES_BLOCK_LEN] = [0; AES_BLOCK_LEN];
+    rng.fill(&mut salt[..]);
+    rng.fill(&mut iv[..]);
+
+    let aes_key: [u8; AES_KEY_LEN] = pbkdf2_(passphrase, &salt, PBKDF2_ITERATIONS);
+    let encrypted: Vec<u8> = Aes256CbcEnc::new(&aes_key.into(), &iv.into()).encrypt_padded_vec_mut::<Pkcs7>(&info);
+
+    let kdf: Vec<u8> = der_sequence(&[
+        der_tlv(OBJECT_IDENTIFIER, &PBKDF2_OID),
+        der_sequence(&[
+            der_tlv(OCTET_STRING, &salt),
+            der_integer(num::BigUint::from(PBKDF2_ITERATIONS)),
+            der_sequence(&[der_tlv(OBJECT_IDENTIFIER, &HMAC_SHA256_OID), der_tlv(NULL, &[])]),
+        ]),
+    ]);
+    let scheme: Vec<u8> = der_sequence(&[der_tlv(OBJECT_IDENTIFIER, &AES256_CBC_OID), der_tlv(OCTET_STRING, &iv)]);
+    Ok(der_sequence(&[
+        der_sequence(&[der_tlv(OBJECT_IDENTIFIER, &PBES2_OID), der_sequence(&[kdf, scheme])]),
+        der_tlv(OCTET_STRING, &encrypted),
+    ]))
+}
+
+/**
+ * Decrypt a PKCS#8 EncryptedPrivateKeyInfo.
+ * Only PBES2 with PBKDF2-HMAC-SHA256 and AES-256-CBC is supported.
+ */
+pub fn private_key_from_encrypted_der<T>(der: &[u8], passphrase: &str) -> Result<PrivateKey<T>, EncodingError>
+where T: RsaInt
+{
+    let mut info: DerReader = DerReader::new(DerReader::new(der).read_last(SEQUENCE)?);
+    let mut algorithm: DerReader = DerReader::new(info.read(SEQUENCE)?);
+    let encrypted: &[u8] = info.read_last(OCTET_STRING)?;
+    if algorithm.read(OBJECT_IDENTIFIER)? != PBES2_OID {
+        return Err(EncodingError::UnsupportedCipher(String::from("not PBES2")));
+    }
+    let mut params: DerReader = DerReader::new(algorithm.read_last(SEQUENCE)?);
+
+    let mut kdf: DerReader = DerReader::new(params.read(SEQUENCE)?);
+    if kdf.read(OBJECT_IDENTIFIER)? != PBKDF2_OID {
+        return Err(EncodingError::UnsupportedCipher(String::from("not PBKDF2")));
+    }
+    let mut kdf_params: DerReader = DerReader::new(kdf.read_last(SEQUENCE)?);
+    let salt: &[u8] = kdf_params.read(OCTET_STRING)?;
+    let iterations: u32 = match kdf_params.read_integer::<num::BigUint>()?.to_u32() {
+        Some(iterations) if iterations > 0 => iterations,
+        _ => return Err(EncodingError::InvalidDer),
+    };
+    if kdf_params.is_empty() {
+        return Err(EncodingError::UnsupportedCipher(String::from("hmacWithSHA1")));
+    }
+    let mut prf: DerReader = DerReader::new(kdf_params.read_last(SEQUENCE)?);
+    if prf.read(OBJECT_IDENTIFIER)? != HMAC_SHA256_OID {
+        return Err(EncodingError::UnsupportedCipher(String::from("PBKDF2 PRF")));
+    }
+    if !prf.is_empty() {
+        prf.read_last(NULL)?;
+    }
+
+    let mut scheme: DerReader = DerReader::new(params.read_last(SEQUENCE)?);
+    if scheme.read(OBJECT_IDENTIFIER)? != AES256_CBC_OID {
+        return Err(EncodingError::UnsupportedCipher(String::from("not AES-256-CBC")));
+    }
+    let iv: &[u8] = scheme.read_last(OCTET_STRING)?;
+    if iv.len() != AES_BLOCK_LEN || encrypted.is_empty() || !encrypted.len().is_multiple_of(AES_BLOCK_LEN) {
+        return Err(EncodingError::InvalidDer);
+    }
+
+    let aes_key: [u8; AES_KEY_LEN] = pbkdf2_(passphrase, salt, iterations);
+    let decrypted: Vec<u8> = match Aes256CbcDec::new(&aes_key.into(), iv.into()).decrypt_padded_vec_mut::<Pkcs7>(encrypted) {
+        Ok(decrypted) => decrypted,
+        Err(_) => return Err(EncodingError::WrongPassphrase),
+    };
+    match private_key_from_der(&decrypted, KeyFormat::Pkcs8) {
+        Err(EncodingError::InvalidDer) => Err(EncodingError::WrongPassphrase),     //the padding was valid by chance
+        result => result,
+    }
+}
+
+/**
+ * PEM encoding of an encrypted private key ("ENCRYPTED PRIVATE KEY").
+ */
+pub fn private_key_to_encrypted_pem<T>(key: &PrivateKey<T>, passphrase: &str) -> Result<String, EncodingError>
+where T: RsaInt
+{
+    Ok(pem_encode(ENCRYPTED_LABEL, &private_key_to_encrypted_der(key, passphrase)?))
+}
+
+/**
+ * PEM decoding of an encrypted private key.
+ */
+pub fn private_key_from_encrypted_pem<T>(pem: &str, passphrase: &str) -> Result<PrivateKey<T>, EncodingError>
+where T: RsaInt
+{
+    let (label, der) = pem_decode(pem)?;
+    if label != ENCRYPTED_LABEL {
+        return Err(EncodingError::UnexpectedLabel(label));
+    }
+    private_key_from_encrypted_der(&der, passphrase)
+}
+
+/**
+ * @return true if pem holds an encrypted private key.
+ */
+pub fn is_encrypted_pem(pem: &str) -> bool {
+    pem.contains(&format!("-----BEGIN {}-----", ENCRYPTED_LABEL))
+}
+
+/**
+ * Wrap DER bytes in a PEM armor with 64 characters lines.
+ */
+pub fn pem_encode(label: &str, der: &[u8]) -> String {
+    let encoded: String = STANDARD.encode(der);
+    let mut pem: String = format!("-----BEGIN {}-----\n", label);
+    for line in encoded.as_bytes().chunks(64) {
+        pem.push_str(&String::from_utf8_lossy(line));
+        pem.push('\n');
+    }
+    pem.push_str(format!("-----END {}-----\n", label).as_str());
+    pem
+}
+
+/**
+ * Read the first PEM block of pem.
+ * @return the label and the DER bytes.
+ */
+pub fn pem_decode(pem: &str) -> Result<(String, Vec<u8>), EncodingError> {
+    let mut lines = pem.lines().map(|line| line.trim()).skip_while(|line| !line.starts_with("-----BEGIN "));
+    let label: String = match lines.next() {
+        Some(line) => match line.strip_prefix("-----BEGIN ").and_then(|l| l.strip_suffix("-----")) {
+            Some(label) => label.to_string(),
+            None => return Err(EncodingError::InvalidPem),
+        },
+        None => return Err(EncodingError::InvalidPem),
+    };
+
+    let end: String = format!("-----END {}-----", label);
+    let mut encoded: String = String::new();
+    for line in lines {
+        if line == end {
+            return match STANDARD.decode(encoded) {
+                Ok(der) => Ok((label, der)),
+                Err(_) => Err(EncodingError::InvalidPem),
+            };
+        }
+        encoded.push_str(line);
+    }
+    Err(EncodingError::InvalidPem)
+}
+
+/**
+ * DER encoding of a tag, its length and its content.
+ */
+pub fn der_tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut der: Vec<u8> = vec![tag];
+    if content.len() < 0x80 {
+        der.push(content.len() as u8);
+    } else {
+        let length: Vec<u8> = content.len().to_be_bytes().into_iter().skip_while(|b| *b == 0).collect();
+        der.push(0x80 | length.len() as u8);
+        der.extend_from_slice(&length);
+    }
+    der.extend_from_slice(content);
+    der
+}
+
+/**
+ * DER encoding of a sequence of already encoded elements.
+ */
+pub fn der_sequence(elements: &[Vec<u8>]) -> Vec<u8> {
+    der_tlv(SEQUENCE, &elements.concat())
+}
+
+/**
+ * DER encoding of a non-negative integer.
+ */
+pub fn der_integer<T>(n: T) -> Vec<u8>
+where T: RsaInt
+{
+    let mut content: Vec<u8> = to_bytes(n.clone(), byte_length(n)).unwrap();
+    if content.is_empty() || content[0] & 0x80 != 0 {
+        content.insert(0, 0x00);    //keep the integer positive
+    }
+    der_tlv(INTEGER, &content)
+}
+
+fn pbkdf2_(passphrase: &str, salt: &[u8], iterations: u32) -> [u8; AES_KEY_LEN] {
+    let mut key: [u8; AES_KEY_LEN] = [0; AES_KEY_LEN];
+    pbkdf2::pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), salt, iterations, &mut key);
+    key
+}
+
+fn rsa_algorithm_identifier_() -> Vec<u8> {
+    der_sequence(&[der_tlv(OBJECT_IDENTIFIER, &RSA_ENCRYPTION_OID), der_tlv(NULL, &[])])
+}
+
+fn read_rsa_algorithm_identifier_(reader: &mut DerReader) -> Result<(), EncodingError> {
+    let mut algorithm: DerReader = DerReader::new(reader.read(SEQUENCE)?);
+    if algorithm.read(OBJECT_IDENTIFIER)? != RSA_ENCRYPTION_OID {
+        return Err(EncodingError::UnsupportedAlgorithm);
+    }
+    if !algorithm.is_empty() {
+        algorithm.read(NULL)?;
+    }
+    algorithm.finish()
+}
+
+/**
+ * Sequential reader of DER elements.
+ */
+pub struct DerReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> DerReader<'a> {
+    /**
+     * Read the elements of data from its start.
+     */
+    pub fn new(data: &'a [u8]) -> DerReader<'a> {
+        DerReader { data }
+    }
+
+    /**
+     * @return true if all the elements have been read.
+     */
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /**
+     * Read the next element, its tag must be tag.
+     * @return the content of the element.
+     */
+    pub fn read(&mut self, tag: u8) -> Result<&'a [u8], EncodingError> {
+        if self.data.len() < 2 || self.data[0] != tag {
+            return Err(EncodingError::InvalidDer);
+        }
+        let (length, header): (usize, usize) = match self.data[1] {
+            l if l < 0x80 => (l as usize, 2),
+            l if l == 0x80 || l > 0x84 => return Err(EncodingError::InvalidDer),
+            l => {
+                let size: usize = (l & 0x7f) as usize;
+                if self.data.len() < 2 + size {
+                    return Err(EncodingError::InvalidDer);
+                }
+                let length: usize = self.data[2..2 + size].iter().fold(0, |acc, b| (acc << 8) | *b as usize);
+                (length, 2 + size)
+            }
+        };
+        if self.data.len() - header < length {
+            return Err(EncodingError::InvalidDer);
+        }
+        let content: &'a [u8] = &self.data[header..header + length];
+        self.data = &self.data[header + length..];
+        Ok(content)
+    }
+
+    /**
+     * Read the next element which must be the last one.
+     */
+    pub fn read_last(&mut self, tag: u8) -> Result<&'a [u8], EncodingError> {
+        let content: &'a [u8] = self.read(tag)?;
+        self.finish()?;
+        Ok(content)
+    }
+
+    /**
+     * Read a non-negative integer.
+     */
+    pub fn read_integer<T>(&mut self) -> Result<T, EncodingError>
+    where T: RsaInt
+    {
+        let mut content: &[u8] = self.read(INTEGER)?;
+        if content.is_empty() || content[0] & 0x80 != 0 {
+            return Err(EncodingError::InvalidDer);      //negative integers are not used by the keys
+        }
+        while content.len() > 1 && content[0] == 0x00 {
+            content = &content[1..];
+        }
+        let n: T = from_bytes(content);
+        if to_bytes(n.clone(), content.len()).as_deref() != Some(content) {
+            return Err(EncodingError::IntegerTooBig);
+        }
+        Ok(n)
+    }
+
+    /**
+     * Check that all the elements have been read.
+     */
+    pub fn finish(&self) -> Result<(), EncodingError> {
+        if self.data.is_empty() {
+            Ok(())
+        } else {
+            Err(EncodingError::InvalidDer)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rsa::{generate_bits, BigRsaInt};
+
+    fn rsa_public_key_(n: u128, e: u128) -> Vec<u8> {
+        der_sequence(&[der_integer(n), der_integer(e)])
+    }
+
+    #[test]
+    fn keys_round_trip_in_both_formats() {
+        let (public, private) = generate_bits::<BigRsaInt>(512).unwrap();
+        for format in [KeyFormat::Pkcs1, KeyFormat::Pkcs8] {
+            let decoded: PublicKey<BigRsaInt> = public_key_from_pem(&public_key_to_pem(&public, format)).unwrap();
+            assert_eq!((decoded.n(), decoded.e()), (public.n(), public.e()));
+            let decoded: PrivateKey<BigRsaInt> = private_key_from_pem(&private_key_to_pem(&private, format).unwrap()).unwrap();
+            assert_eq!((decoded.n(), decoded.d(), decoded.p()), (private.n(), private.d(), private.p()));
+        }
+    }
+
+    #[test]
+    fn degenerate_public_keys_are_rejected() {
+        for (n, e) in [(0, 3), (1, 3), (3233, 0), (3233, 1), (3233, 4), (3234, 17), (3233, 3233), (3233, 5000)] {
+            assert_eq!(public_key_from_der::<u128>(&rsa_public_key_(n, e), KeyFormat::Pkcs1).err(), Some(EncodingError::InvalidKey), "n = {}, e = {}", n, e);
+        }
+        assert!(public_key_from_der::<u128>(&rsa_public_key_(3233, 17), KeyFormat::Pkcs1).is_ok());
+    }
+
+    #[test]
+    fn private_key_with_an_overflowing_modulus_is_rejected() {
+        let big: u128 = u128::MAX / 3;
+        let ints: Vec<Vec<u8>> = [0, 35, 5, 5, big, big - 2, 1, 1, 1].iter().map(|&x| der_integer(x)).collect();
+        assert_eq!(private_key_from_der::<u128>(&der_sequence(&ints), KeyFormat::Pkcs1).err(), Some(EncodingError::InvalidKey));
+    }
+}
@@ -0,0 +1,127 @@
+use base64::{engine::general_purpose::STANDARD_NO_PAD, Engine as _};
+use sha2::{Digest, Sha256};
+
+use crate::openssh::public_key_blob;
+use crate::rsa::{PublicKey, RsaInt};
+use crate::rsa_tools::{bit_length, to_hex};
+
+//size of the randomart field, as ssh-keygen
+const FIELD_WIDTH : usize = 17;
+const FIELD_HEIGHT : usize = 9;
+//symbols by number of visits, the two last ones mark the start and the end
+const SYMBOLS : &[u8] = b" .o+=*BOX@%&#/^SE";
+
+/**
+ * SHA-256 fingerprint of a public key.
+ * The digest is computed over the OpenSSH wire encoding of the key, as ssh-keygen -l,
+ * so the same key has the same fingerprint whatever the file format it comes from.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fingerprint {
+    digest: Vec<u8>,
+    bits: usize,        //size of the key, written in the randomart
+}
+
+impl Fingerprint {
+    /**
+     * Compute the fingerprint of a public key.
+     */
+    pub fn of<T>(key: &PublicKey<T>) -> Fingerprint
+    where T: RsaInt
+    {
+        Fingerprint {
+            digest: Sha256::digest(public_key_blob(key)).to_vec(),
+            bits: bit_length(key.n()),
+        }
+    }
+
+    /**
+     * @return the 32 bytes of the SHA-256 digest.
+     */
+    pub fn digest(&self) -> &[u8] {
+        &self.digest
+    }
+
+    /**
+     * @return the digest in hexadecimal, the bytes separated by ':'.
+     */
+    pub fn to_hex(&self) -> String {
+        self.digest.iter().map(|byte| format!("{:02x}", byte)).collect::<Vec<String>>().join(":")
+    }
+
+    /**
+     * @return the digest in base64 without padding, prefixed by "SHA256:" as ssh-keygen -l.
+     */
+    pub fn to_base64(&self) -> String {
+        format!("SHA256:{}", STANDARD_NO_PAD.encode(&self.digest))
+    }
+
+    /**
+     * @return the first hexadecimal digits of the digest.
+     */
+    pub fn short(&self, digits: usize) -> String {
+        let hex: String = to_hex(&self.digest);
+        hex[..digits.min(hex.len())].to_string()
+    }
+
+    /**
+     * Draw the digest with the "drunken bishop" algorithm of OpenSSH:
+     * a bishop starts at the center of the field and moves diagonally
+     * for each pair of bits of the digest, the cells count its visits.
+     * Two different keys give drawings which are easy to tell apart.
+     */
+    pub fn randomart(&self) -> String {
+        let mut field: [[usize; FIELD_HEIGHT]; FIELD_WIDTH] = [[0; FIELD_HEIGHT]; FIELD_WIDTH];
+        let (mut x, mut y): (usize, usize) = (FIELD_WIDTH / 2, FIELD_HEIGHT / 2);
+        let start: (usize, usize) = (x, y);
+        let max_visits: usize = SYMBOLS.len() - 3;
+
+        for byte in &self.digest {
+            let mut input: u8 = *byte;
+            for _ in 0..4 {
+                x = if input & 0x1 != 0 { (x + 1).min(FIELD_WIDTH - 1) } else { x.saturating_sub(1) };
+                y = if input & 0x2 != 0 { (y + 1).min(FIELD_HEIGHT - 1) } else { y.saturating_sub(1) };
+                field[x][y] = (field[x][y] + 1).min(max_visits);
+                input >>= 2;
+            }
+        }
+        field[start.0][start.1] = SYMBOLS.len() - 2;
+        field[x][y] = SYMBOLS.len() - 1;
+
+        let mut art: String = border_(&format!("[RSA {}]", self.bits));
+        for row in 0..FIELD_HEIGHT {
+            art.push('|');
+            for column in field.iter() {
+                art.push(SYMBOLS[column[row]] as char);
+            }
+            art.push_str("|\n");
+        }
+        art.push_str(&border_("[SHA256]"));
+        art
+    }
+}
+
+impl std::fmt::Display for Fingerprint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_base64())
+    }
+}
+
+fn border_(title: &str) -> String {
+    let left: usize = FIELD_WIDTH.saturating_sub(title.len()) / 2;
+    let right: usize = FIELD_WIDTH.saturating_sub(title.len() + left);
+    format!("+{}{}{}+\n", "-".repeat(left), title, "-".repeat(right))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rsa::BigRsaInt;
+
+    #[test]
+    fn fingerprint_matches_ssh_keygen() {
+        let n: BigRsaInt = "123356852611244953407242654856992544181754519537558523382062920056866161852023247136363537425427368145678200895493276522776583605677665382299002987615314930670857469605020661036204533638593066793386039629831207449716272902698932251915157685787215719239089416266774883478389483995277215341266256472524743351979".parse().unwrap();
+        let key: PublicKey<BigRsaInt> = PublicKey::construct(BigRsaInt::from(65537u32), n);
+        assert_eq!(Fingerprint::of(&key).to_base64(), "SHA256:3JRG77/othMocXKP7PsjkhtyvwaP6z6E3NzN2fsxMpY");
+    }
+}
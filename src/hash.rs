@@ -0,0 +1,77 @@
+use sha2::{Digest, Sha256, Sha384, Sha512};
+
+/**
+ * Hash functions available for the padding and signature schemes.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hash {
+    /** SHA-256, 32 bytes digest */
+    Sha256,
+    /** SHA-384, 48 bytes digest */
+    Sha384,
+    /** SHA-512, 64 bytes digest */
+    Sha512,
+}
+
+impl Hash {
+    /**
+     * @return the digest of data.
+     */
+    pub fn digest(&self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Hash::Sha256 => Sha256::digest(data).to_vec(),
+            Hash::Sha384 => Sha384::digest(data).to_vec(),
+            Hash::Sha512 => Sha512::digest(data).to_vec(),
+        }
+    }
+
+    /**
+     * @return the DER encoding of the DigestInfo header (algorithm identifier
+     * and digest octet string tag) placed before the digest in PKCS#1 v1.5 signatures.
+     */
+    pub fn digest_info_prefix(&self) -> &'static [u8] {
+        match self {
+            Hash::Sha256 => &[0x30, 0x31, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01, 0x05, 0x00, 0x04, 0x20],
+            Hash::Sha384 => &[0x30, 0x41, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x02, 0x05, 0x00, 0x04, 0x30],
+            Hash::Sha512 => &[0x30, 0x51, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x03, 0x05, 0x00, 0x04, 0x40],
+        }
+    }
+
+    /**
+     * @return the name of the hash function.
+     */
+    pub fn name(&self) -> &'static str {
+        match self {
+            Hash::Sha256 => "SHA-256",
+            Hash::Sha384 => "SHA-384",
+            Hash::Sha512 => "SHA-512",
+        }
+    }
+
+    /**
+     * @return the size of a digest in bytes.
+     */
+    pub fn output_len(&self) -> usize {
+        match self {
+            Hash::Sha256 => 32,
+            Hash::Sha384 => 48,
+            Hash::Sha512 => 64,
+        }
+    }
+}
+
+impl std::str::FromStr for Hash {
+    type Err = String;
+
+    /**
+     * Read a hash name like "sha256" or "SHA-256".
+     */
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().replace('-', "").as_str() {
+            "sha256" => Ok(Hash::Sha256),
+            "sha384" => Ok(Hash::Sha384),
+            "sha512" => Ok(Hash::Sha512),
+            _ => Err(format!("unknown hash function {}", s)),
+        }
+    }
+}
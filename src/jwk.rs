@@ -0,0 +1,273 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use serde::{Deserialize, Serialize};
+
+use crate::encoding::EncodingError;
+use crate::rsa::{PrivateKey, PublicKey, RsaInt};
+use crate::rsa_tools::{byte_length, from_bytes, to_bytes};
+
+/**
+ * A RSA JSON Web Key (RFC 7517, RFC 7518).
+ * The numbers are written in base64url without padding.
+ */
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Jwk {
+    kty: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    kid: Option<String>,
+    #[serde(rename = "use", skip_serializing_if = "Option::is_none")]
+    usage: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    alg: Option<String>,
+    n: String,
+    e: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    d: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    p: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    q: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dp: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dq: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    qi: Option<String>,
+}
+
+/**
+ * A JWK Set: {"keys": [...]}
+ */
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JwkSet {
+    keys: Vec<Jwk>,
+}
+
+impl Jwk {
+    /**
+     * @return the key ID ("kid" member).
+     */
+    pub fn kid(&self) -> Option<&str> {
+        self.kid.as_deref()
+    }
+
+    /**
+     * Set or remove the key ID ("kid" member).
+     */
+    pub fn set_kid(&mut self, kid: Option<&str>) {
+        self.kid = kid.map(|kid| kid.to_string());
+    }
+
+    /**
+     * Set or remove the intended use of the key ("use" member : "sig" or "enc").
+     */
+    pub fn set_usage(&mut self, usage: Option<&str>) {
+        self.usage = usage.map(|usage| usage.to_string());
+    }
+
+    /**
+     * Set or remove the algorithm of the key ("alg" member, e.g. "RS256").
+     */
+    pub fn set_alg(&mut self, alg: Option<&str>) {
+        self.alg = alg.map(|alg| alg.to_string());
+    }
+
+    /**
+     * @return true if the JWK contains the private exponent.
+     */
+    pub fn is_private(&self) -> bool {
+        self.d.is_some()
+    }
+
+    /**
+     * @return the JWK without its private members.
+     */
+    pub fn to_public(&self) -> Jwk {
+        Jwk { d: None, p: None, q: None, dp: None, dq: None, qi: None, ..self.clone() }
+    }
+
+    /**
+     * @return the JWK as pretty-printed JSON.
+     */
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    /**
+     * Parse a JWK, the RSA members are checked when the key is converted.
+     */
+    pub fn from_json(json: &str) -> Result<Jwk, EncodingError> {
+        serde_json::from_str(json).map_err(|e| EncodingError::InvalidJson(e.to_string()))
+    }
+}
+
+impl JwkSet {
+    /**
+     * Construct a JWK Set from its keys.
+     */
+    pub fn construct(keys: Vec<Jwk>) -> JwkSet {
+        JwkSet { keys }
+    }
+
+    /**
+     * @return the keys of the set.
+     */
+    pub fn keys(&self) -> &[Jwk] {
+        &self.keys
+    }
+
+    /**
+     * @return the key with the given kid.
+     */
+    pub fn find(&self, kid: &str) -> Option<&Jwk> {
+        self.keys.iter().find(|key| key.kid() == Some(kid))
+    }
+
+    /**
+     * @return the JWK Set as pretty-printed JSON.
+     */
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    /**
+     * Parse a JWK Set.
+     */
+    pub fn from_json(json: &str) -> Result<JwkSet, EncodingError> {
+        serde_json::from_str(json).map_err(|e| EncodingError::InvalidJson(e.to_string()))
+    }
+}
+
+/**
+ * JWK of a public key.
+ */
+pub fn public_key_to_jwk<T>(key: &PublicKey<T>, kid: Option<&str>) -> Jwk
+where T: RsaInt
+{
+    Jwk {
+        kty: String::from("RSA"),
+        kid: kid.map(|kid| kid.to_string()),
+        usage: None,
+        alg: None,
+        n: encode_(key.n()),
+        e: encode_(key.e()),
+        d: None,
+        p: None,
+        q: None,
+        dp: None,
+        dq: None,
+        qi: None,
+    }
+}
+
+/**
+ * JWK of a private key, the CRT members are written when they are known.
+ */
+pub fn private_key_to_jwk<T>(key: &PrivateKey<T>, kid: Option<&str>) -> Jwk
+where T: RsaInt
+{
+    let mut jwk: Jwk = public_key_to_jwk(&key.public_key(), kid);
+    jwk.d = Some(encode_(key.d()));
+    jwk.p = key.p().map(encode_);
+    jwk.q = key.q().map(encode_);
+    jwk.dp = key.dp().map(encode_);
+    jwk.dq = key.dq().map(encode_);
+    jwk.qi = key.qinv().map(encode_);
+    jwk
+}
+
+/**
+ * Read the public key of a JWK (the private members are ignored).
+ */
+pub fn public_key_from_jwk<T>(jwk: &Jwk) -> Result<PublicKey<T>, EncodingError>
+where T: RsaInt
+{
+    if jwk.kty != "RSA" {
+        return Err(EncodingError::UnsupportedAlgorithm);
+    }
+    let public: PublicKey<T> = PublicKey::construct(decode_(&jwk.e)?, decode_(&jwk.n)?);
+    if !public.is_valid() {
+        return Err(EncodingError::InvalidKey);
+    }
+    Ok(public)
+}
+
+/**
+ * Read the private key of a JWK.
+ * Without p and q, the key only has the private exponent d.
+ */
+pub fn private_key_from_jwk<T>(jwk: &Jwk) -> Result<PrivateKey<T>, EncodingError>
+where T: RsaInt
+{
+    let public: PublicKey<T> = public_key_from_jwk(jwk)?;
+    let d: T = match &jwk.d {
+        Some(d) => decode_(d)?,
+        None => return Err(EncodingError::InvalidKey),
+    };
+    match (&jwk.p, &jwk.q) {
+        (Some(p), Some(q)) => {
+            let p: T = decode_(p)?;
+            let q: T = decode_(q)?;
+            if p <= T::one() || q <= T::one() || p == q || p.checked_mul(&q) != Some(public.n()) {
+                return Err(EncodingError::InvalidKey);
+            }
+            PrivateKey::construct_crt(p, q, public.e(), d).map_err(|_| EncodingError::InvalidKey)
+        }
+        (None, None) => Ok(PrivateKey::construct(public.n(), public.e(), d)),
+        _ => Err(EncodingError::InvalidKey),
+    }
+}
+
+fn encode_<T>(n: T) -> String
+where T: RsaInt
+{
+    let len: usize = byte_length(n.clone()).max(1);
+    URL_SAFE_NO_PAD.encode(to_bytes(n, len).unwrap())
+}
+
+fn decode_<T>(s: &str) -> Result<T, EncodingError>
+where T: RsaInt
+{
+    let bytes: Vec<u8> = match URL_SAFE_NO_PAD.decode(s) {
+        Ok(bytes) => bytes,
+        Err(e) => return Err(EncodingError::InvalidJson(e.to_string())),
+    };
+    let n: T = from_bytes(&bytes);
+    if to_bytes(n.clone(), bytes.len()).as_deref() != Some(&bytes[..]) {
+        return Err(EncodingError::IntegerTooBig);
+    }
+    Ok(n)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rsa::{generate_bits, BigRsaInt};
+
+    fn jwk_(n: u128, e: u128, private: Option<(u128, u128, u128)>) -> Jwk {
+        let b64 = |x: u128| URL_SAFE_NO_PAD.encode(x.to_be_bytes());
+        let mut json: String = format!(r#"{{"kty":"RSA","n":"{}","e":"{}""#, b64(n), b64(e));
+        if let Some((d, p, q)) = private {
+            json.push_str(&format!(r#","d":"{}","p":"{}","q":"{}""#, b64(d), b64(p), b64(q)));
+        }
+        json.push('}');
+        Jwk::from_json(&json).unwrap()
+    }
+
+    #[test]
+    fn keys_round_trip() {
+        let (public, private) = generate_bits::<BigRsaInt>(512).unwrap();
+        let decoded: PublicKey<BigRsaInt> = public_key_from_jwk(&Jwk::from_json(&public_key_to_jwk(&public, Some("k1")).to_json()).unwrap()).unwrap();
+        assert_eq!((decoded.n(), decoded.e()), (public.n(), public.e()));
+        let decoded: PrivateKey<BigRsaInt> = private_key_from_jwk(&Jwk::from_json(&private_key_to_jwk(&private, None).to_json()).unwrap()).unwrap();
+        assert_eq!((decoded.n(), decoded.d(), decoded.q()), (private.n(), private.d(), private.q()));
+    }
+
+    #[test]
+    fn degenerate_keys_are_rejected() {
+        for (n, e) in [(0, 3), (1, 3), (3233, 0), (3233, 1), (3233, 4), (3234, 17), (3233, 3233)] {
+            assert_eq!(public_key_from_jwk::<u128>(&jwk_(n, e, None)).err(), Some(EncodingError::InvalidKey), "n = {}, e = {}", n, e);
+        }
+        let big: u128 = u128::MAX / 3;
+        assert_eq!(private_key_from_jwk::<u128>(&jwk_(35, 5, Some((5, big, big - 2)))).err(), Some(EncodingError::InvalidKey));
+    }
+}
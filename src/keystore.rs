@@ -0,0 +1,399 @@
+use std::path::{Path, PathBuf};
+
+use chrono::prelude::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::encoding::EncodingError;
+use crate::rsa::{load_private_key, load_public_key, save_private_key, save_public_key, BigRsaInt, PrivateKey, PublicKey};
+use crate::rsa_tools::bit_length;
+
+const INDEX_FILE : &str = "index.json";
+const KEY_ID_LEN : usize = 16;      //hexadecimal digits of the fingerprint kept in the key ID
+/** Environment variable overriding the default keyring directory */
+pub const KEYRING_ENV : &str = "PRIME_TOOLS_KEYRING";
+
+/**
+ * Errors returned by the keyring.
+ * It converts into RsaError::Keystore.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeystoreError {
+    /** The keyring directory can not be read or written */
+    Io(String),
+    /** The index file is malformed */
+    InvalidIndex(String),
+    /** A key file can not be read or written */
+    Encoding(EncodingError),
+    /** No key matches the name */
+    UnknownKey(String),
+    /** Several keys match the name */
+    AmbiguousKey(String),
+    /** The key is already in the keyring */
+    DuplicateKey(String),
+    /** The label is already used by another key */
+    DuplicateLabel(String),
+    /** The keyring only has the public key */
+    NotPrivate(String),
+}
+
+impl std::fmt::Display for KeystoreError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeystoreError::Io(e) => write!(f, "keyring error : {}", e),
+            KeystoreError::InvalidIndex(e) => write!(f, "invalid keyring index : {}", e),
+            KeystoreError::Encoding(e) => write!(f, "{}", e),
+            KeystoreError::UnknownKey(name) => write!(f, "no key named {}", name),
+            KeystoreError::AmbiguousKey(name) => write!(f, "several keys match {}", name),
+            KeystoreError::DuplicateKey(id) => write!(f, "the key {} is already in the keyring", id),
+            KeystoreError::DuplicateLabel(label) => write!(f, "the label {} is already used", label),
+            KeystoreError::NotPrivate(id) => write!(f, "the keyring has no private key for {}", id),
+        }
+    }
+}
+
+impl std::error::Error for KeystoreError {}
+
+impl From<EncodingError> for KeystoreError {
+    fn from(e: EncodingError) -> KeystoreError {
+        KeystoreError::Encoding(e)
+    }
+}
+
+/**
+ * Index entry of a key.
+ */
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyEntry {
+    id: String,         //start of the SHA-256 fingerprint of the public key
+    label: String,
+    created: String,    //RFC 3339 creation date
+    bits: usize,
+    private: bool,      //the file holds the private key
+    encrypted: bool,    //the private key is protected by a passphrase
+}
+
+impl KeyEntry {
+    /**
+     * @return the key ID, the first hexadecimal digits of the fingerprint.
+     */
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /**
+     * @return the name given to the key.
+     */
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /**
+     * @return the RFC 3339 creation date.
+     */
+    pub fn created(&self) -> &str {
+        &self.created
+    }
+
+    /**
+     * @return the size of the modulus.
+     */
+    pub fn bits(&self) -> usize {
+        self.bits
+    }
+
+    /**
+     * @return true if the keyring holds the private key.
+     */
+    pub fn is_private(&self) -> bool {
+        self.private
+    }
+
+    /**
+     * @return true if the private key file is protected by a passphrase.
+     */
+    pub fn is_encrypted(&self) -> bool {
+        self.encrypted
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Index {
+    keys: Vec<KeyEntry>,
+}
+
+/**
+ * A directory of key files described by an index.
+ * The private keys are stored as PKCS#8 (encrypted with a passphrase or not),
+ * the public keys as SubjectPublicKeyInfo.
+ */
+pub struct Keyring {
+    dir: PathBuf,
+    entries: Vec<KeyEntry>,
+}
+
+impl Keyring {
+    /**
+     * Open the keyring of dir, it is created if it does not exist.
+     */
+    pub fn open(dir: &Path) -> Result<Keyring, KeystoreError> {
+        create_dir_(dir)?;
+        let index: PathBuf = dir.join(INDEX_FILE);
+        let entries: Vec<KeyEntry> = if index.exists() {
+            let content: String = std::fs::read_to_string(&index).map_err(|e| KeystoreError::Io(e.to_string()))?;
+            match serde_json::from_str::<Index>(&content) {
+                Ok(index) => index.keys,
+                Err(e) => return Err(KeystoreError::InvalidIndex(e.to_string())),
+            }
+        } else {
+            Vec::new()
+        };
+        //the file names are built from the IDs, they must not leave the directory
+        if let Some(entry) = entries.iter().find(|entry| !is_key_id_(&entry.id)) {
+            return Err(KeystoreError::InvalidIndex(format!("{:?} is not a key ID", entry.id)));
+        }
+        Ok(Keyring { dir: dir.to_path_buf(), entries })
+    }
+
+
+    /**
+     * Open the keyring of $PRIME_TOOLS_KEYRING, or of ~/.prime_tools/keyring.
+     */
+    pub fn open_default() -> Result<Keyring, KeystoreError> {
+        Keyring::open(&default_dir())
+    }
+
+    /**
+     * @return the directory of the keyring.
+     */
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /**
+     * @return the index entries, in the order the keys were added.
+     */
+    pub fn entries(&self) -> &[KeyEntry] {
+        &self.entries
+    }
+
+    /**
+     * Find a key by its label or by a prefix of its ID.
+     */
+    pub fn find(&self, name: &str) -> Result<&KeyEntry, KeystoreError> {
+        if let Some(entry) = self.entries.iter().find(|entry| entry.label == name) {
+            return Ok(entry);
+        }
+        let name_lower: String = name.to_lowercase();
+        let mut matches = self.entries.iter().filter(|entry| !name.is_empty() && entry.id.starts_with(&name_lower));
+        match (matches.next(), matches.next()) {
+            (Some(entry), None) => Ok(entry),
+            (Some(_), Some(_)) => Err(KeystoreError::AmbiguousKey(name.to_string())),
+            (None, _) => Err(KeystoreError::UnknownKey(name.to_string())),
+        }
+    }
+
+    /**
+     * Store a private key, its public key is stored beside it.
+     * @param passphrase encrypt the key file with this passphrase.
+     *
+     * @return the index entry of the key.
+     */
+    pub fn add_private(&mut self, key: &PrivateKey<BigRsaInt>, label: &str, passphrase: Option<&str>) -> Result<KeyEntry, KeystoreError> {
+        let entry: KeyEntry = self.new_entry_(&key.public_key(), label, true, passphrase.is_some())?;
+        save_private_key(&self.path_(&entry), key, passphrase)?;
+        save_public_key(&self.public_path_(&entry), &key.public_key())?;
+        self.push_(entry)
+    }
+
+    /**
+     * Store a public key.
+     * @return the index entry of the key.
+     */
+    pub fn add_public(&mut self, key: &PublicKey<BigRsaInt>, label: &str) -> Result<KeyEntry, KeystoreError> {
+        let entry: KeyEntry = self.new_entry_(key, label, false, false)?;
+        save_public_key(&self.public_path_(&entry), key)?;
+        self.push_(entry)
+    }
+
+    /**
+     * Load the public key of a stored key (private keys included),
+     * no passphrase is needed.
+     */
+    pub fn public_key(&self, name: &str) -> Result<PublicKey<BigRsaInt>, KeystoreError> {
+        let entry: &KeyEntry = self.find(name)?;
+        Ok(load_public_key(&self.public_path_(entry))?)
+    }
+
+    /**
+     * Load a stored private key.
+     * @param passphrase the passphrase of an encrypted key.
+     */
+    pub fn private_key(&self, name: &str, passphrase: Option<&str>) -> Result<PrivateKey<BigRsaInt>, KeystoreError> {
+        let entry: &KeyEntry = self.find(name)?;
+        if !entry.private {
+            return Err(KeystoreError::NotPrivate(entry.id.clone()));
+        }
+        Ok(load_private_key(&self.path_(entry), passphrase)?)
+    }
+
+    /**
+     * Remove a key and its files from the keyring.
+     * @return the removed entry.
+     */
+    pub fn delete(&mut self, name: &str) -> Result<KeyEntry, KeystoreError> {
+        let id: String = self.find(name)?.id.clone();
+        let position: usize = self.entries.iter().position(|entry| entry.id == id).unwrap();
+        let entry: KeyEntry = self.entries.remove(position);
+        self.save_index_()?;
+        for path in [self.path_(&entry), self.public_path_(&entry)] {
+            match std::fs::remove_file(path) {
+                Ok(()) => {},
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {},
+                Err(e) => return Err(KeystoreError::Io(e.to_string())),
+            }
+        }
+        Ok(entry)
+    }
+
+    fn new_entry_(&self, key: &PublicKey<BigRsaInt>, label: &str, private: bool, encrypted: bool) -> Result<KeyEntry, KeystoreError> {
+        let id: String = key_id(key);
+        if self.entries.iter().any(|entry| entry.id == id) {
+            return Err(KeystoreError::DuplicateKey(id));
+        }
+        if self.entries.iter().any(|entry| entry.label == label) {
+            return Err(KeystoreError::DuplicateLabel(label.to_string()));
+        }
+        let now: DateTime<Utc> = Utc::now();
+        Ok(KeyEntry {
+            id,
+            label: label.to_string(),
+            created: now.to_rfc3339_opts(SecondsFormat::Secs, true),
+            bits: bit_length(key.n()),
+            private,
+            encrypted,
+        })
+    }
+
+    fn push_(&mut self, entry: KeyEntry) -> Result<KeyEntry, KeystoreError> {
+        self.entries.push(entry.clone());
+        self.save_index_()?;
+        Ok(entry)
+    }
+
+    fn path_(&self, entry: &KeyEntry) -> String {
+        self.dir.join(format!("{}.pem", entry.id)).to_string_lossy().to_string()
+    }
+
+    fn public_path_(&self, entry: &KeyEntry) -> String {
+        self.dir.join(format!("{}.pub", entry.id)).to_string_lossy().to_string()
+    }
+
+    /**
+     * Write the index in a temporary file then rename it,
+     * so that an interrupted write does not lose the index.
+     */
+    fn save_index_(&self) -> Result<(), KeystoreError> {
+        let index: Index = Index { keys: self.entries.clone() };
+        let json: String = serde_json::to_string_pretty(&index).unwrap();
+        let tmp: PathBuf = self.dir.join(format!("{}.tmp", INDEX_FILE));
+        std::fs::write(&tmp, json)
+            .and_then(|_| std::fs::rename(&tmp, self.dir.join(INDEX_FILE)))
+            .map_err(|e| KeystoreError::Io(e.to_string()))
+    }
+}
+
+/**
+ * ID of a key : the first hexadecimal digits of its fingerprint.
+ */
+pub fn key_id(key: &PublicKey<BigRsaInt>) -> String {
+    key.fingerprint().short(KEY_ID_LEN)
+}
+
+/**
+ * @return true if id has the form of a key ID, KEY_ID_LEN lowercase hexadecimal digits.
+ */
+fn is_key_id_(id: &str) -> bool {
+    id.len() == KEY_ID_LEN && id.bytes().all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+}
+
+/**
+ * @return $PRIME_TOOLS_KEYRING, or ~/.prime_tools/keyring.
+ */
+pub fn default_dir() -> PathBuf {
+    match std::env::var_os(KEYRING_ENV) {
+        Some(dir) => PathBuf::from(dir),
+        None => config_dir().join("keyring"),
+    }
+}
+
+/**
+ * @return the directory of the files of prime_tools : ~/.prime_tools
+ */
+pub fn config_dir() -> PathBuf {
+    let home: PathBuf = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE")).map(PathBuf::from).unwrap_or_default();
+    home.join(".prime_tools")
+}
+
+fn create_dir_(dir: &Path) -> Result<(), KeystoreError> {
+    if dir.is_dir() {
+        return Ok(());
+    }
+    let mut builder: std::fs::DirBuilder = std::fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+    builder.create(dir).map_err(|e| KeystoreError::Io(format!("{} : {}", dir.display(), e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rsa::generate_bits;
+
+    fn temp_dir_(name: &str) -> std::path::PathBuf {
+        let dir: std::path::PathBuf = std::env::temp_dir().join(format!("prime_tools_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn keys_are_found_by_label_and_id() {
+        let dir: std::path::PathBuf = temp_dir_("keyring");
+        let (_, private) = generate_bits::<BigRsaInt>(256).unwrap();
+        let mut keyring: Keyring = Keyring::open(&dir).unwrap();
+        let id: String = keyring.add_private(&private, "alice", None).unwrap().id().to_string();
+
+        let keyring: Keyring = Keyring::open(&dir).unwrap();
+        assert_eq!(keyring.public_key("alice").unwrap().n(), private.n());
+        assert_eq!(keyring.private_key(&id[..6], None).unwrap().d(), private.d());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn an_index_with_a_path_as_id_is_rejected() {
+        let dir: std::path::PathBuf = temp_dir_("evil_keyring");
+        std::fs::create_dir_all(&dir).unwrap();
+        let index: &str = r#"{"keys":[{"id":"../../victim","label":"evil","created":"","bits":0,"private":true,"encrypted":false}]}"#;
+        std::fs::write(dir.join("index.json"), index).unwrap();
+        let result = Keyring::open(&dir).map(|_| ());
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(matches!(result, Err(KeystoreError::InvalidIndex(_))));
+    }
+
+    #[test]
+    fn the_file_stored_in_the_index_is_ignored() {
+        let dir: std::path::PathBuf = temp_dir_("old_keyring");
+        std::fs::create_dir_all(&dir).unwrap();
+        let index: &str = r#"{"keys":[{"id":"0123456789abcdef","label":"old","created":"","bits":0,"private":true,"encrypted":false,"file":"victim"}]}"#;
+        std::fs::write(dir.join("index.json"), index).unwrap();
+        std::fs::write(dir.join("victim"), "kept").unwrap();
+        let mut keyring: Keyring = Keyring::open(&dir).unwrap();
+        keyring.delete("old").unwrap();
+        let kept: bool = dir.join("victim").exists();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(kept);
+    }
+}
@@ -0,0 +1,139 @@
+use std::path::{Path, PathBuf};
+
+use crate::fingerprint::Fingerprint;
+use crate::keystore::config_dir;
+use crate::rsa::RsaError;
+
+/** Environment variable overriding the default known hosts file */
+pub const KNOWN_HOSTS_ENV : &str = "PRIME_TOOLS_KNOWN_HOSTS";
+
+/**
+ * What the sender does when the key of a known receiver has changed.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HostKeyPolicy {
+    /** Close the connection */
+    Refuse,
+    /** Warn, then record the new key and go on */
+    Warn,
+}
+
+/**
+ * Result of the check of a receiver key.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HostStatus {
+    /** The fingerprint is the recorded one */
+    Known,
+    /** First contact with this address */
+    Unknown,
+    /** The address is recorded with this other fingerprint */
+    Changed(String),
+}
+
+/**
+ * Trust on first use : the fingerprints of the receiver keys by address,
+ * stored one per line as `<address> <fingerprint>`.
+ */
+pub struct KnownHosts {
+    path: PathBuf,
+    hosts: Vec<(String, String)>,
+}
+
+impl KnownHosts {
+    /**
+     * Read the known hosts file, a missing file is an empty list.
+     */
+    pub fn open(path: &Path) -> Result<KnownHosts, RsaError> {
+        let content: String = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(RsaError::Io(format!("{} : {}", path.display(), e))),
+        };
+        let mut hosts: Vec<(String, String)> = Vec::new();
+        for line in content.lines().map(|line| line.trim()).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+            match line.split_whitespace().collect::<Vec<&str>>()[..] {
+                [addr, fingerprint] => hosts.push((addr.to_string(), fingerprint.to_string())),
+                _ => return Err(RsaError::Io(format!("{} : invalid known host line : {}", path.display(), line))),
+            }
+        }
+        Ok(KnownHosts { path: path.to_path_buf(), hosts })
+    }
+
+    /**
+     * Read $PRIME_TOOLS_KNOWN_HOSTS, or ~/.prime_tools/known_hosts.
+     */
+    pub fn open_default() -> Result<KnownHosts, RsaError> {
+        KnownHosts::open(&default_path())
+    }
+
+    /**
+     * @return the path of the known hosts file.
+     */
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /**
+     * @return the recorded fingerprint of addr.
+     */
+    pub fn get(&self, addr: &str) -> Option<&str> {
+        self.hosts.iter().find(|(host, _)| host == addr).map(|(_, fingerprint)| fingerprint.as_str())
+    }
+
+    /**
+     * Compare the fingerprint of a receiver with the recorded one.
+     */
+    pub fn check(&self, addr: &str, fingerprint: &Fingerprint) -> HostStatus {
+        match self.get(addr) {
+            Some(known) if known == fingerprint.to_string() => HostStatus::Known,
+            Some(known) => HostStatus::Changed(known.to_string()),
+            None => HostStatus::Unknown,
+        }
+    }
+
+    /**
+     * Record the fingerprint of addr (replacing the previous one) and save the file.
+     */
+    pub fn add(&mut self, addr: &str, fingerprint: &Fingerprint) -> Result<(), RsaError> {
+        self.hosts.retain(|(host, _)| host != addr);
+        self.hosts.push((addr.to_string(), fingerprint.to_string()));
+        self.save_()
+    }
+
+    /**
+     * Forget addr and save the file.
+     * @return false if addr was not recorded.
+     */
+    pub fn remove(&mut self, addr: &str) -> Result<bool, RsaError> {
+        let len: usize = self.hosts.len();
+        self.hosts.retain(|(host, _)| host != addr);
+        if self.hosts.len() == len {
+            return Ok(false);
+        }
+        self.save_().map(|_| true)
+    }
+
+    fn save_(&self) -> Result<(), RsaError> {
+        let mut content: String = String::new();
+        for (addr, fingerprint) in &self.hosts {
+            content.push_str(&format!("{} {}\n", addr, fingerprint));
+        }
+        match self.path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            Some(dir) => std::fs::create_dir_all(dir),
+            None => Ok(()),
+        }
+        .and_then(|_| std::fs::write(&self.path, content))
+        .map_err(|e| RsaError::Io(format!("{} : {}", self.path.display(), e)))
+    }
+}
+
+/**
+ * @return $PRIME_TOOLS_KNOWN_HOSTS, or ~/.prime_tools/known_hosts.
+ */
+pub fn default_path() -> PathBuf {
+    match std::env::var_os(KNOWN_HOSTS_ENV) {
+        Some(path) => PathBuf::from(path),
+        None => config_dir().join("known_hosts"),
+    }
+}
@@ -0,0 +1,100 @@
+/*!
+ * Prime numbers and RSA tools.
+ *
+ * The crate generates and tests prime numbers, generates RSA keys for any
+ * integer type implementing [`rsa::RsaInt`] (the primitive unsigned integers
+ * and [`rsa::BigRsaInt`]), encrypts and signs with the PKCS#1 paddings,
+ * seals data of any size with RSA and an authenticated cipher,
+ * reads and writes the keys in the usual formats and exchanges encrypted
+ * messages over TCP.
+ *
+ * ```
+ * use prime_tools::padding::{oaep_decrypt, oaep_encrypt};
+ * use prime_tools::hash::Hash;
+ * use prime_tools::rsa::{generate_bits, BigRsaInt};
+ *
+ * let (public, private) = generate_bits::<BigRsaInt>(1024).unwrap();
+ * let cipher = oaep_encrypt(b"hello", &public, Hash::Sha256, b"").unwrap();
+ * assert_eq!(oaep_decrypt(&cipher, &private, Hash::Sha256, b"").unwrap(), b"hello");
+ * ```
+ *
+ * ```
+ * use prime_tools::rsa::{generate_bits, open, seal, BigRsaInt, ContentCipher, KeyWrap};
+ *
+ * let (public, private) = generate_bits::<BigRsaInt>(1024).unwrap();
+ * let sealed = seal(&[42; 100_000], &public, KeyWrap::Oaep, ContentCipher::Aes256Gcm).unwrap();
+ * assert_eq!(open(&sealed, &private).unwrap(), vec![42; 100_000]);
+ * ```
+ */
+
+#![warn(missing_docs)]
+
+/**
+ * RSA keys : generation, textbook and hybrid encryption (seal and open), key files and the RsaError type.
+ */
+pub mod rsa;
+
+/**
+ * Miller-Rabin primality test.
+ */
+pub mod primality;
+
+/**
+ * Random prime numbers of a number of digits or bits.
+ */
+pub mod prime_generator;
+
+/**
+ * Modular arithmetic : exponentiation, Montgomery form, inverses and byte conversions.
+ */
+pub mod rsa_tools;
+
+/**
+ * TCP receiver and sender of RSA encrypted messages.
+ */
+pub mod network;
+
+/**
+ * Hash functions used by the paddings and the signatures.
+ */
+pub mod hash;
+
+/**
+ * PKCS#1 v1.5 and OAEP encryption paddings.
+ */
+pub mod padding;
+
+/**
+ * PKCS#1 v1.5 and PSS signatures.
+ */
+pub mod signature;
+
+/**
+ * DER and PEM encodings of the keys (PKCS#1, PKCS#8, encrypted PKCS#8).
+ */
+pub mod encoding;
+
+/**
+ * OpenSSH public and private key formats.
+ */
+pub mod openssh;
+
+/**
+ * JSON Web Keys.
+ */
+pub mod jwk;
+
+/**
+ * On-disk keyring of keys identified by their fingerprint.
+ */
+pub mod keystore;
+
+/**
+ * SHA-256 fingerprints of the public keys.
+ */
+pub mod fingerprint;
+
+/**
+ * Trust on first use of the receiver keys.
+ */
+pub mod known_hosts;
@@ -1,99 +1,379 @@
-mod primality;
-mod prime_generator;
-mod tools;
-mod rsa;
-mod network;
-
-use network::network::{NetworkListener, NetworkWriter};
-use crate::primality::primality::is_prime_;
-use crate::prime_generator::gen::generator;
-use crate::rsa::rsa::{generate, encrypt_tab, decrypt_tab};
+use std::io::{BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 
+use base64::{engine::general_purpose::STANDARD, Engine as _};
 use chrono::prelude::{DateTime, Local};
+use clap::builder::RangedU64ValueParser;
+use clap::{ArgGroup, Parser, Subcommand, ValueEnum};
+
+use prime_tools::encoding::{
+    is_encrypted_pem, private_key_from_encrypted_pem, private_key_from_pem, private_key_to_encrypted_pem,
+    private_key_to_pem, public_key_from_pem, public_key_to_pem, EncodingError, KeyFormat,
+};
+use prime_tools::hash::Hash;
+use prime_tools::jwk::{private_key_from_jwk, private_key_to_jwk, public_key_from_jwk, Jwk, JwkSet};
+use prime_tools::keystore::{config_dir, KeyEntry, Keyring};
+use prime_tools::known_hosts::{HostKeyPolicy, KnownHosts};
+use prime_tools::network::{print_fingerprint, NetworkListener, NetworkWriter, Protocol, IDENTITY_KEY_BITS, OAEP_KEY_BITS};
+use prime_tools::openssh::{private_key_from_openssh, private_key_to_openssh, public_key_from_openssh, public_key_to_openssh};
+use prime_tools::primality::is_prime_;
+use prime_tools::prime_generator::{generator, generator_bits};
+use prime_tools::rsa::{
+    generate, generate_bits, encrypt_tab, decrypt_tab, load_private_key, load_public_key, open, open_stream, seal, seal_stream,
+    save_private_key, write_key_file, BigRsaInt, ContentCipher, KeyWrap, PrivateKey, PublicKey, RsaError,
+};
+use prime_tools::rsa_tools::{bit_length, from_hex, to_hex};
+use prime_tools::signature::{pss_sign, pss_verify, sign, verify};
+
+//size of the keys generated to sign a message
+const SIGNATURE_KEY_BITS : usize = 2048;
+//smallest key size of keygen and keyring generate, smaller keys cannot use e = 65537 or RSA-OAEP
+const MIN_KEY_BITS : u64 = 512;
+//address of the receiver
+const DEFAULT_ADDR : &str = "127.0.0.1:1234";
+//passphrase used instead of asking it on the terminal (empty for no passphrase), for scripts
+const PASSPHRASE_ENV : &str = "PRIME_TOOLS_PASSPHRASE";
+//key of the receiver when serve is launched without --key, created on first use in ~/.prime_tools
+const SERVE_KEY_FILE : &str = "serve_key.pem";
+//exit codes, clap exits with 2 on a usage error
+const EXIT_ERROR : u8 = 1;
+const EXIT_REJECTED : u8 = 3;       //the number is not prime, or the signature is not valid
+
+type CliResult = Result<ExitCode, Box<dyn std::error::Error>>;
+
+/** Prime number and RSA tools */
+#[derive(Parser)]
+#[command(name = "prime_tools", version, after_help = "Exit status: 0 on success, 1 on error, 2 on a usage error, 3 if the number is not prime or the signature is not valid.")]
+struct Cli {
+    /** Print the time taken by the operation on stderr */
+    #[arg(long, global = true)]
+    time: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /** Generate a key pair and print it, or save it in <FILE> and <FILE>.pub */
+    Keygen {
+        /** Size of the modulus */
+        #[arg(short, long, default_value_t = 2048, value_parser = RangedU64ValueParser::<usize>::new().range(MIN_KEY_BITS..))]
+        bits: usize,
+        #[arg(short, long, value_enum, default_value_t = KeyFileFormat::Pkcs8)]
+        format: KeyFileFormat,
+        /** Save the keys, a PKCS#8 or OpenSSH private key is encrypted with the passphrase asked or read from $PRIME_TOOLS_PASSPHRASE (empty for none) */
+        #[arg(short, long, value_name = "FILE")]
+        out: Option<String>,
+    },
+    /** Encrypt a file for a recipient : a random content key encrypts the data and is wrapped with RSA */
+    Encrypt {
+        /** Encrypt this message instead of a file. Without a recipient, it is encrypted then decrypted with a new key */
+        #[arg(required_unless_present = "recipient", conflicts_with = "input")]
+        message: Option<String>,
+        /** Public key file, or key of the keyring */
+        #[arg(short, long, value_name = "KEY", allow_hyphen_values = true)]
+        recipient: Option<String>,
+        /** File to encrypt [default: stdin] */
+        #[arg(short, long = "in", value_name = "FILE", requires = "recipient")]
+        input: Option<PathBuf>,
+        /** Encrypted file [default: stdout] */
+        #[arg(short, long, value_name = "FILE", requires = "recipient")]
+        out: Option<PathBuf>,
+        /** Wrapping of the content key : oaep or kem (RSA-KEM) */
+        #[arg(long, default_value = "oaep")]
+        wrap: KeyWrap,
+        /** Cipher of the data : aes-256-gcm or chacha20-poly1305 */
+        #[arg(long, default_value = "aes-256-gcm")]
+        cipher: ContentCipher,
+        /** Encrypt each byte of the message with textbook RSA and a new small key */
+        #[arg(long, conflicts_with = "recipient")]
+        textbook: bool,
+    },
+    /** Decrypt a file encrypted by encrypt */
+    Decrypt {
+        /** Private key file, or key of the keyring */
+        #[arg(short, long)]
+        key: String,
+        /** Encrypted file [default: stdin] */
+        #[arg(short, long = "in", value_name = "FILE")]
+        input: Option<PathBuf>,
+        /** Decrypted file [default: stdout] */
+        #[arg(short, long, value_name = "FILE")]
+        out: Option<PathBuf>,
+    },
+    /** Sign a message (RSASSA-PKCS1-v1_5, or RSASSA-PSS with --pss) */
+    Sign {
+        message: String,
+        /** Private key file, or key of the keyring. Without it, a new key is generated and its public key printed */
+        #[arg(short, long)]
+        key: Option<String>,
+        #[arg(long, default_value = "sha256")]
+        hash: Hash,
+        #[arg(long)]
+        pss: bool,
+        #[arg(long, value_enum, default_value_t = TextFormat::Hex)]
+        output: TextFormat,
+    },
+    /** Verify the signature of a message */
+    Verify {
+        message: String,
+        signature: String,
+        /** Public key file, key of the keyring, or public key printed by sign */
+        #[arg(short, long, allow_hyphen_values = true)]
+        key: String,
+        #[arg(long, default_value = "sha256")]
+        hash: Hash,
+        #[arg(long)]
+        pss: bool,
+        #[arg(long, value_enum, default_value_t = TextFormat::Hex)]
+        input: TextFormat,
+    },
+    /** Test or generate prime numbers */
+    Prime {
+        #[command(subcommand)]
+        command: PrimeCommand,
+    },
+    /** Launch the encrypted TCP receiver */
+    Serve {
+        #[arg(short, long, default_value = DEFAULT_ADDR)]
+        addr: String,
+        /** Private key file or key of the keyring used as identity, instead of ~/.prime_tools/serve_key.pem */
+        #[arg(short, long)]
+        key: Option<String>,
+    },
+    /** Connect to the receiver and send encrypted messages, its key is checked against the known hosts */
+    Connect {
+        #[arg(short, long, default_value = DEFAULT_ADDR)]
+        addr: String,
+        /** Encryption of the messages : sealed (hybrid), RSA-OAEP blocks, or textbook RSA bytes */
+        #[arg(long, value_enum, default_value_t = Mode::Seal)]
+        mode: Mode,
+        /** Wrapping of the content keys of the sealed messages : oaep or kem (RSA-KEM) */
+        #[arg(long, default_value = "oaep")]
+        wrap: KeyWrap,
+        /** Cipher of the sealed messages : aes-256-gcm or chacha20-poly1305 */
+        #[arg(long, default_value = "aes-256-gcm")]
+        cipher: ContentCipher,
+        /** Accept a changed receiver key with a warning instead of refusing it */
+        #[arg(long)]
+        accept_new_key: bool,
+        /** Known hosts file [default: $PRIME_TOOLS_KNOWN_HOSTS or ~/.prime_tools/known_hosts] */
+        #[arg(long, value_name = "FILE")]
+        known_hosts: Option<PathBuf>,
+    },
+    /** Print the components and the fingerprint of a PEM, encrypted PEM, OpenSSH, ssh-rsa or JWK key file */
+    Inspect {
+        file: String,
+        /** Passphrase of an encrypted key, asked if needed */
+        #[arg(short, long)]
+        passphrase: Option<String>,
+    },
+    /** Manage the keyring ($PRIME_TOOLS_KEYRING or ~/.prime_tools/keyring) */
+    Keyring {
+        #[command(subcommand)]
+        command: KeyringCommand,
+    },
+}
+
+#[derive(Subcommand)]
+enum PrimeCommand {
+    /** Check if a number is prime */
+    Test {
+        #[arg(allow_hyphen_values = true)]
+        number: String,
+    },
+    /** Generate a prime number of n digits, or of n bits */
+    #[command(group(ArgGroup::new("size").required(true).args(["digits", "bits"])))]
+    Gen {
+        #[arg(value_parser = clap::value_parser!(u16).range(1..))]
+        digits: Option<u16>,
+        #[arg(short, long, value_parser = RangedU64ValueParser::<usize>::new().range(2..))]
+        bits: Option<usize>,
+    },
+}
+
+#[derive(Subcommand)]
+enum KeyringCommand {
+    /** List the keys */
+    List,
+    /** Generate a private key, encrypted with the passphrase asked or read from $PRIME_TOOLS_PASSPHRASE (empty for none) */
+    Generate {
+        label: String,
+        #[arg(short, long, default_value_t = 2048, value_parser = RangedU64ValueParser::<usize>::new().range(MIN_KEY_BITS..))]
+        bits: usize,
+    },
+    /** Import a public or private key file */
+    Import {
+        file: String,
+        label: String,
+    },
+    /** Print a public key, or the private key with --private */
+    Export {
+        key: String,
+        #[arg(long)]
+        private: bool,
+    },
+    /** Delete a key */
+    Delete {
+        key: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum KeyFileFormat {
+    Pkcs1,      //PEM RSA PRIVATE KEY and RSA PUBLIC KEY
+    Pkcs8,      //PEM PRIVATE KEY and PUBLIC KEY
+    Encrypted,  //PEM ENCRYPTED PRIVATE KEY, always asks a passphrase
+    Openssh,    //OPENSSH PRIVATE KEY and ssh-rsa line
+    Jwk,        //JSON Web Key and JWK Set of the public key
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Mode {
+    Seal,       //hybrid encryption, see Protocol::Seal
+    Oaep,       //RSA-OAEP blocks
+    Textbook,   //one textbook RSA block per byte
+}
 
-fn main() {
-    //get users args and check if their are enough
-    let args : Vec<String> = std::env::args().collect();
-    if args.len() < 2 {
-        println!("Usage: {} <mode> [number]", args[0]);
-        println!("--help to have more informations");
-        return;
+/**
+ * Text encoding of the binary outputs (encrypted blocks, signatures).
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum TextFormat {
+    Hex,
+    Base64,
+}
+
+impl TextFormat {
+    fn encode(&self, bytes: &[u8]) -> String {
+        match self {
+            TextFormat::Hex => to_hex(bytes),
+            TextFormat::Base64 => STANDARD.encode(bytes),
+        }
     }
 
+    /**
+     * @return the error message if s is not written in this format.
+     */
+    fn decode(&self, s: &str) -> Result<Vec<u8>, String> {
+        match self {
+            TextFormat::Hex => from_hex(s).ok_or_else(|| format!("{} is not written in hexadecimal", s)),
+            TextFormat::Base64 => STANDARD.decode(s).map_err(|_| format!("{} is not written in base64", s)),
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let cli: Cli = Cli::parse();
+
     //get the local time at the beginning of the program
     let start: DateTime<Local> = Local::now();
 
     //Process
-    match args[1].as_str() {
-        "--help" => { help(); },
-        "--primality" => {
-            if args.len() < 3 {
-                println!("Usage: {} {} <number>", args[0], args[1]);
-                return;
-            }
-            check_primality(&args[2]);
-        },
-        "--generator" => {
-            if args.len() < 3 {
-                println!("Usage: {} {} <number>", args[0], args[1]);
-                return;
-            }
-            generate_prime(&args[2]);
-        },
-        "--encrypt" => {
-        if args.len() < 3 {
-            println!("Usage: {} {} <message>", args[0], args[1]);
-            return;
-        }
-        encrypt(&args[2]);
+    let status: ExitCode = match run(cli.command) {
+        Ok(status) => status,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::from(EXIT_ERROR)
         }
-        "--receiver" => {
-            active_receiver();
-        }
-        "--sender" => {
-            active_sender();
-        }
-
-        _ => { println!("Unknown mode. Use --help to have more informations"); },
     };
 
     //print the delta time at the end of the program
-    let delta_time: chrono::Duration = Local::now() - start;
-    let mut  millis: i64 = delta_time.num_milliseconds();
-    let s: i64 = millis / 1000;
-    millis %= 1000;
-    if s != 0 || millis != 0 {
-        println!("Operation performed in {}s {}ms", s, millis);
-    } else {
-        let micros = delta_time.num_microseconds().unwrap();
-        println!("Operation performed in {}microseconds", micros);
+    if cli.time {
+        let delta_time: chrono::Duration = Local::now() - start;
+        let mut  millis: i64 = delta_time.num_milliseconds();
+        let s: i64 = millis / 1000;
+        millis %= 1000;
+        if s != 0 || millis != 0 {
+            eprintln!("Operation performed in {}s {}ms", s, millis);
+        } else {
+            let micros = delta_time.num_microseconds().unwrap();
+            eprintln!("Operation performed in {}microseconds", micros);
+        }
     }
-
+    status
 }
 
-fn help() {
-    println!("Welcome to a prime number tools !");
-    println!("");
-    println!("--primality : check if a number is prime");
-    println!("--generator : generate a prime number");
-    println!("--encrypt : encrypt and uncrypt a message");
-    println!("--receiver : launch a crypted TCP server");
-    println!("--sender : connect to the receiver and allow to send crypted message");
-    println!("--help : display this help");
+fn run(command: Command) -> CliResult {
+    match command {
+        Command::Keygen { bits, format, out } => generate_keys(bits, format, out.as_deref()),
+        Command::Encrypt { message, recipient, input, out, wrap, cipher, textbook } => match (recipient, message) {
+            (Some(recipient), message) => encrypt_file(&recipient, message.as_deref(), input.as_deref(), out.as_deref(), wrap, cipher),
+            (None, Some(message)) if textbook => encrypt(&message),
+            (None, Some(message)) => encrypt_sealed(&message, wrap, cipher),
+            (None, None) => unreachable!("clap requires the message or the recipient"),
+        },
+        Command::Decrypt { key, input, out } => decrypt_file(&key, input.as_deref(), out.as_deref()),
+        Command::Sign { message, key, hash, pss, output } => sign_message(&message, key.as_deref(), hash, pss, output),
+        Command::Verify { message, signature, key, hash, pss, input } => verify_message(&message, &signature, &key, hash, pss, input),
+        Command::Prime { command: PrimeCommand::Test { number } } => check_primality(&number),
+        Command::Prime { command: PrimeCommand::Gen { digits, bits } } => match (digits, bits) {
+            (_, Some(bits)) => generate_prime_bits(bits),
+            (Some(digits), None) => generate_prime(digits),
+            (None, None) => unreachable!("clap requires the digits or the bits"),
+        },
+        Command::Serve { addr, key } => active_receiver(&addr, key.as_deref()),
+        Command::Connect { addr, mode, wrap, cipher, accept_new_key, known_hosts } => {
+            let policy: HostKeyPolicy = if accept_new_key { HostKeyPolicy::Warn } else { HostKeyPolicy::Refuse };
+            let protocol: Protocol = match mode {
+                Mode::Seal => Protocol::Seal(wrap, cipher),
+                Mode::Oaep => Protocol::Oaep,
+                Mode::Textbook => Protocol::Textbook,
+            };
+            active_sender(&addr, protocol, policy, known_hosts.as_deref())
+        }
+        Command::Inspect { file, passphrase } => inspect_key(&file, passphrase.as_deref()),
+        Command::Keyring { command } => keyring_command(command),
+    }
 }
 
-fn active_receiver() {
-    let listener = NetworkListener::new("127.0.0.1:1234");
+fn active_receiver(addr: &str, key: Option<&str>) -> CliResult {
+    let identity: PrivateKey<BigRsaInt> = match key {
+        Some(name) => resolve_private_key(name)?,
+        None => serve_identity()?,
+    };
+    if bit_length(identity.n()) < IDENTITY_KEY_BITS {
+        return Err(format!("the key of the receiver has {} bits, it must have at least {} bits", bit_length(identity.n()), IDENTITY_KEY_BITS).into());
+    }
+    let mut listener: NetworkListener = NetworkListener::new(addr)?;
+    listener.set_identity(identity);
     listener.listen();
+    Ok(ExitCode::SUCCESS)
 }
 
-fn active_sender() {
-    let mut writer = NetworkWriter::new("127.0.0.1:1234");
-    writer.listen();
+/**
+ * Load the default key of the receiver, it is generated and saved on first use
+ * so that the senders recognize the receiver from one connection to the next.
+ */
+fn serve_identity() -> Result<PrivateKey<BigRsaInt>, Box<dyn std::error::Error>> {
+    let path: PathBuf = config_dir().join(SERVE_KEY_FILE);
+    let path_str: &str = path.to_str().ok_or("the path of the receiver key is not valid UTF-8")?;
+    if path.exists() {
+        return Ok(load_private_key(path_str, None)?);
+    }
+    println!("generate the receiver key");
+    let (_, private) = generate_bits::<BigRsaInt>(IDENTITY_KEY_BITS)?;
+    std::fs::create_dir_all(config_dir())?;
+    save_private_key(path_str, &private, None)?;
+    println!("Receiver key saved in {}", path.display());
+    Ok(private)
 }
 
-fn encrypt(s: &String) {
-    let (public, private) = generate::<u128>(9);
+fn active_sender(addr: &str, protocol: Protocol, policy: HostKeyPolicy, known_hosts: Option<&Path>) -> CliResult {
+    let mut writer: NetworkWriter = NetworkWriter::new(addr)?;
+    let known_hosts: KnownHosts = match known_hosts {
+        Some(path) => KnownHosts::open(path)?,
+        None => KnownHosts::open_default()?,
+    };
+    writer.set_known_hosts(known_hosts, policy);
+    writer.listen(protocol)?;
+    Ok(ExitCode::SUCCESS)
+}
+
+fn encrypt(s: &str) -> CliResult {
+    let (public, private) = generate::<u128>(9)?;
 
     println!("public key : {}|{}", public.e(), public.n());
     println!("private public : {}", private.d());
@@ -101,41 +381,371 @@ fn encrypt(s: &String) {
     let tab = s.as_bytes();
     let encrypted_message = encrypt_tab(tab, &public);
     println!("encrypted message : {:?}", encrypted_message);
-    let decrypted_message = decrypt_tab(&encrypted_message, &public, &private);
+    let decrypted_message = decrypt_tab(&encrypted_message, &private);
     let mut decrypt_string : String = String::new();
-    for i in 0..decrypted_message.len() {
-        decrypt_string.push(decrypted_message[i] as u8 as char);
+    for c in decrypted_message {
+        decrypt_string.push(c as u8 as char);
     }
 
     println!("decrypted message : {:?}", decrypt_string);
+    Ok(ExitCode::SUCCESS)
+}
+
+fn encrypt_sealed(s: &str, wrap: KeyWrap, cipher: ContentCipher) -> CliResult {
+    let (public, private) = generate_bits::<BigRsaInt>(OAEP_KEY_BITS)?;
+
+    println!("public key : {}|{}", public.e(), public.n());
+    println!("private key : {}", private.d());
+
+    let sealed: Vec<u8> = seal(s.as_bytes(), &public, wrap, cipher)?;
+    println!("{} and {} encrypted message ({} bytes) : {}", wrap.name(), cipher.name(), sealed.len(), to_hex(&sealed));
+
+    let decrypted_message: Vec<u8> = open(&sealed, &private)?;
+    println!("decrypted message : {:?}", String::from_utf8_lossy(&decrypted_message));
+    Ok(ExitCode::SUCCESS)
+}
+
+/**
+ * Encrypt the message, or the input file (stdin without it), in the output file (stdout without it).
+ */
+fn encrypt_file(recipient: &str, message: Option<&str>, input: Option<&Path>, out: Option<&Path>, wrap: KeyWrap, cipher: ContentCipher) -> CliResult {
+    let public: PublicKey<BigRsaInt> = resolve_public_key(recipient)?;
+    let mut reader: Box<dyn Read> = match (message, input) {
+        (Some(message), _) => Box::new(std::io::Cursor::new(message.as_bytes().to_vec())),
+        (None, Some(path)) => Box::new(open_input(path)?),
+        (None, None) => Box::new(std::io::stdin().lock()),
+    };
+    let len: u64 = with_output(out, |writer| seal_stream(&mut reader, writer, &public, wrap, cipher))?;
+    if let Some(path) = out {
+        println!("{} bytes encrypted in {}", len, path.display());
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/**
+ * Decrypt the input file (stdin without it) in the output file (stdout without it).
+ */
+fn decrypt_file(key: &str, input: Option<&Path>, out: Option<&Path>) -> CliResult {
+    let private: PrivateKey<BigRsaInt> = resolve_private_key(key)?;
+    let mut reader: Box<dyn Read> = match input {
+        Some(path) => Box::new(open_input(path)?),
+        None => Box::new(std::io::stdin().lock()),
+    };
+    let len: u64 = with_output(out, |writer| open_stream(&mut reader, writer, &private))?;
+    if let Some(path) = out {
+        println!("{} bytes decrypted in {}", len, path.display());
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn open_input(path: &Path) -> Result<std::fs::File, String> {
+    std::fs::File::open(path).map_err(|e| format!("{} : {}", path.display(), e))
+}
+
+/**
+ * Run write on the output file, or on stdout without it.
+ * The output file is removed if write fails, so that no partial file is left.
+ */
+fn with_output<F>(out: Option<&Path>, write: F) -> Result<u64, Box<dyn std::error::Error>>
+where F: FnOnce(&mut dyn Write) -> Result<u64, RsaError>
+{
+    let path: &Path = match out {
+        Some(path) => path,
+        None => return Ok(write(&mut BufWriter::new(std::io::stdout().lock()))?),
+    };
+    let file: std::fs::File = std::fs::File::create(path).map_err(|e| format!("{} : {}", path.display(), e))?;
+    match write(&mut BufWriter::new(file)) {
+        Ok(len) => Ok(len),
+        Err(e) => {
+            let _ = std::fs::remove_file(path);
+            Err(e.into())
+        }
+    }
+}
+
+fn sign_message(s: &str, key: Option<&str>, hash: Hash, pss: bool, output: TextFormat) -> CliResult {
+    let private: PrivateKey<BigRsaInt> = match key {
+        Some(name) => resolve_private_key(name)?,
+        None => {
+            let (public, private) = generate_bits::<BigRsaInt>(SIGNATURE_KEY_BITS)?;
+            println!("public key : {}", public);
+            private
+        }
+    };
+
+    let signature: Vec<u8> = if pss {
+        pss_sign(s.as_bytes(), &private, hash, hash, hash.output_len())?
+    } else {
+        sign(s.as_bytes(), &private, hash)?
+    };
+    if key.is_some() {
+        println!("{}", output.encode(&signature));
+    } else {
+        println!("{}{} signature : {}", hash.name(), if pss { " PSS" } else { "" }, output.encode(&signature));
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn verify_message(s: &str, signature: &str, key: &str, hash: Hash, pss: bool, input: TextFormat) -> CliResult {
+    let signature: Vec<u8> = input.decode(signature)?;
+    let public: PublicKey<BigRsaInt> = resolve_public_key(key)?;
+
+    let verified = if pss {
+        pss_verify(s.as_bytes(), &signature, &public, hash, hash, hash.output_len())
+    } else {
+        verify(s.as_bytes(), &signature, &public, hash)
+    };
+    match verified {
+        Ok(()) => {
+            println!("The signature is valid");
+            Ok(ExitCode::SUCCESS)
+        }
+        Err(e) => {
+            println!("The signature is not valid : {}", e);
+            Ok(ExitCode::from(EXIT_REJECTED))
+        }
+    }
+}
+
+/**
+ * Print a new key pair, or save it in out and out.pub.
+ * @param format the encrypted format always asks a passphrase,
+ * PKCS#8 and OpenSSH ask one only when the keys are saved.
+ */
+fn generate_keys(bits: usize, format: KeyFileFormat, out: Option<&str>) -> CliResult {
+    let passphrase: Option<String> = match (format, out) {
+        (KeyFileFormat::Encrypted, _) => Some(prompt_passphrase(true)?.ok_or("an encrypted key needs a passphrase")?),
+        (KeyFileFormat::Pkcs8 | KeyFileFormat::Openssh, Some(_)) => prompt_passphrase(true)?,
+        _ => None,
+    };
+    let (public, private) = generate_bits::<BigRsaInt>(bits)?;
+
+    let (private_text, public_text): (String, String) = match format {
+        KeyFileFormat::Pkcs1 => (private_key_to_pem(&private, KeyFormat::Pkcs1)?, public_key_to_pem(&public, KeyFormat::Pkcs1)),
+        KeyFileFormat::Pkcs8 | KeyFileFormat::Encrypted => {
+            let pem: String = match passphrase.as_deref() {
+                Some(passphrase) => private_key_to_encrypted_pem(&private, passphrase)?,
+                None => private_key_to_pem(&private, KeyFormat::Pkcs8)?,
+            };
+            (pem, public_key_to_pem(&public, KeyFormat::Pkcs8))
+        }
+        KeyFileFormat::Openssh => (
+            private_key_to_openssh(&private, "prime_tools", passphrase.as_deref())?,
+            format!("{}\n", public_key_to_openssh(&public, "prime_tools")),
+        ),
+        KeyFileFormat::Jwk => {
+            let jwk: Jwk = private_key_to_jwk(&private, None);
+            (format!("{}\n", jwk.to_json()), format!("{}\n", JwkSet::construct(vec![jwk.to_public()]).to_json()))
+        }
+    };
+
+    match out {
+        Some(path) => {
+            let public_path: String = format!("{}.pub", path);
+            write_key_file(path, &private_text, true)?;
+            write_key_file(&public_path, &public_text, false)?;
+            println!("Keys saved in {} and {}", path, public_path);
+        }
+        None => print!("{}{}", private_text, public_text),
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn inspect_key(path: &str, passphrase: Option<&str>) -> CliResult {
+    let content: String = std::fs::read_to_string(path).map_err(|e| format!("{} : {}", path, e))?;
+    let mut passphrase: Option<String> = passphrase.map(String::from);
+    if passphrase.is_none() && is_encrypted_pem(&content) {
+        passphrase = prompt_passphrase(false)?;
+    }
+
+    let (kind, public, private): (&str, PublicKey<BigRsaInt>, Option<PrivateKey<BigRsaInt>>) =
+        if content.trim_start().starts_with('{') {
+            let jwk: Jwk = match JwkSet::from_json(&content) {
+                Ok(set) => set.keys().first().cloned().ok_or(EncodingError::InvalidKey)?,
+                Err(_) => Jwk::from_json(&content)?,
+            };
+            if jwk.is_private() {
+                let private: PrivateKey<BigRsaInt> = private_key_from_jwk(&jwk)?;
+                ("JWK private key", private.public_key(), Some(private))
+            } else {
+                ("JWK public key", public_key_from_jwk(&jwk)?, None)
+            }
+        } else if content.trim_start().starts_with("ssh-rsa") {
+            ("OpenSSH public key", public_key_from_openssh::<BigRsaInt>(&content)?.0, None)
+        } else if content.contains("OPENSSH PRIVATE KEY") {
+            let key: Result<(PrivateKey<BigRsaInt>, String), EncodingError> = match private_key_from_openssh(&content, passphrase.as_deref()) {
+                Err(EncodingError::MissingPassphrase) => private_key_from_openssh(&content, prompt_passphrase(false)?.as_deref()),
+                key => key,
+            };
+            let (private, _) = key?;
+            ("OpenSSH private key", private.public_key(), Some(private))
+        } else if is_encrypted_pem(&content) {
+            let private: PrivateKey<BigRsaInt> = private_key_from_encrypted_pem(&content, passphrase.as_deref().ok_or(EncodingError::MissingPassphrase)?)?;
+            ("Encrypted PEM private key", private.public_key(), Some(private))
+        } else if content.contains("PRIVATE KEY") {
+            let private: PrivateKey<BigRsaInt> = private_key_from_pem(&content)?;
+            ("PEM private key", private.public_key(), Some(private))
+        } else {
+            ("PEM public key", public_key_from_pem(&content)?, None)
+        };
+
+    println!("{} ({} bits)", kind, bit_length(public.n()));
+    print_fingerprint(&public);
+    println!("n : {}", public.n());
+    println!("e : {}", public.e());
+    if let Some(private) = private {
+        println!("d : {}", private.d());
+        if let (Some(p), Some(q)) = (private.p(), private.q()) {
+            println!("p : {}", p);
+            println!("q : {}", q);
+        }
+    }
+    Ok(ExitCode::SUCCESS)
 }
 
-fn generate_prime(s : &String) {
-    let n: u8 = match s.parse::<u8>() {
-        Ok(n) => n,
+/**
+ * Load a private key from a key file, or from the default keyring if there is no such file.
+ * The passphrase is asked if the key is encrypted.
+ */
+fn resolve_private_key(name: &str) -> Result<PrivateKey<BigRsaInt>, Box<dyn std::error::Error>> {
+    if !Path::new(name).exists() {
+        return keyring_private_key(&Keyring::open_default()?, name);
+    }
+    match load_private_key(name, None) {
+        Err(EncodingError::MissingPassphrase) => Ok(load_private_key(name, prompt_passphrase(false)?.as_deref())?),
+        key => Ok(key?),
+    }
+}
+
+/**
+ * Load a public key from a key file (the public key of a private key file is accepted),
+ * from the --BEGIN_PUBLIC_KEY--||n||e||--END_PUBLIC_KEY-- text printed by sign, or from the default keyring.
+ */
+fn resolve_public_key(name: &str) -> Result<PublicKey<BigRsaInt>, Box<dyn std::error::Error>> {
+    if Path::new(name).exists() {
+        return match load_public_key(name) {
+            Ok(public) => Ok(public),
+            Err(_) => resolve_private_key(name).map(|private| private.public_key()),
+        };
+    }
+    if let Ok(public) = name.parse::<PublicKey<BigRsaInt>>() {
+        return Ok(public);
+    }
+    Ok(Keyring::open_default()?.public_key(name)?)
+}
+
+/**
+ * Load a private key of the keyring, the passphrase is asked if the key is encrypted.
+ */
+fn keyring_private_key(keyring: &Keyring, name: &str) -> Result<PrivateKey<BigRsaInt>, Box<dyn std::error::Error>> {
+    let passphrase: Option<String> = match keyring.find(name) {
+        Ok(entry) if entry.is_encrypted() => prompt_passphrase(false)?,
+        _ => None,
+    };
+    Ok(keyring.private_key(name, passphrase.as_deref())?)
+}
+
+fn keyring_command(command: KeyringCommand) -> CliResult {
+    let mut keyring: Keyring = Keyring::open_default()?;
+    match command {
+        KeyringCommand::List => {
+            println!("keyring : {}", keyring.dir().display());
+            for entry in keyring.entries() {
+                let kind: &str = match (entry.is_private(), entry.is_encrypted()) {
+                    (true, true) => "private (encrypted)",
+                    (true, false) => "private",
+                    _ => "public",
+                };
+                println!("{}  {:<20} {:>5} bits  {:<19}  {}", entry.id(), entry.label(), entry.bits(), kind, entry.created());
+            }
+        }
+        KeyringCommand::Generate { label, bits } => {
+            let passphrase: Option<String> = prompt_passphrase(true)?;
+            let (_, private) = generate_bits::<BigRsaInt>(bits)?;
+            let entry: KeyEntry = keyring.add_private(&private, &label, passphrase.as_deref())?;
+            println!("Key {} generated", entry.id());
+        }
+        KeyringCommand::Import { file, label } => match load_public_key::<BigRsaInt>(&file) {
+            Ok(public) => {
+                let entry: KeyEntry = keyring.add_public(&public, &label)?;
+                println!("Public key {} imported", entry.id());
+            }
+            Err(_) => {
+                let mut private: Result<PrivateKey<BigRsaInt>, EncodingError> = load_private_key(&file, None);
+                if matches!(private, Err(EncodingError::MissingPassphrase)) {
+                    println!("Passphrase of {}", file);
+                    private = load_private_key(&file, prompt_passphrase(false)?.as_deref());
+                }
+                let private: PrivateKey<BigRsaInt> = private?;
+                println!("Passphrase of the keyring copy (empty to store it unencrypted)");
+                let passphrase: Option<String> = prompt_passphrase(true)?;
+                let entry: KeyEntry = keyring.add_private(&private, &label, passphrase.as_deref())?;
+                println!("Private key {} imported", entry.id());
+            }
+        },
+        KeyringCommand::Export { key, private } => {
+            if private {
+                print!("{}", private_key_to_pem(&keyring_private_key(&keyring, &key)?, KeyFormat::Pkcs8)?);
+            } else {
+                print!("{}", public_key_to_pem(&keyring.public_key(&key)?, KeyFormat::Pkcs8));
+            }
+        }
+        KeyringCommand::Delete { key } => {
+            let entry: KeyEntry = keyring.delete(&key)?;
+            println!("Key {} ({}) deleted", entry.id(), entry.label());
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/**
+ * Read a passphrase from $PRIME_TOOLS_PASSPHRASE, or from the terminal without echoing it.
+ * @param confirm ask the passphrase twice.
+ *
+ * @return None if the passphrase is empty.
+ */
+fn prompt_passphrase(confirm: bool) -> std::io::Result<Option<String>> {
+    let passphrase: String = match std::env::var(PASSPHRASE_ENV) {
+        Ok(passphrase) => passphrase,
         Err(_) => {
-            println!("Error: {} is not a number", s);
-            return;
+            let passphrase: String = read_passphrase("Passphrase : ")?;
+            if confirm && read_passphrase("Confirm passphrase : ")? != passphrase {
+                return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "the passphrases do not match"));
+            }
+            passphrase
         }
     };
+    Ok(if passphrase.is_empty() { None } else { Some(passphrase) })
+}
+
+fn read_passphrase(prompt: &str) -> std::io::Result<String> {
+    rpassword::prompt_password(prompt).map_err(|e| std::io::Error::new(e.kind(),
+        format!("no terminal to ask the passphrase ({}), set {} instead (empty for no passphrase)", e, PASSPHRASE_ENV)))
+}
+
+fn generate_prime(n: u16) -> CliResult {
     println!("Generating...");
-    let prime: u128 = generator(n);
+    let prime: BigRsaInt = generator(n);
     println!("And... Here is your prime number : {}", prime);
+    Ok(ExitCode::SUCCESS)
+}
+
+fn generate_prime_bits(n: usize) -> CliResult {
+    println!("Generating...");
+    let prime: BigRsaInt = generator_bits(n)?;
+    println!("And... Here is your {} bits prime number : {}", n, prime);
+    Ok(ExitCode::SUCCESS)
 }
 
-fn check_primality(s: &String) {
-    if s.starts_with("-") {
+fn check_primality(s: &str) -> CliResult {
+    if s.starts_with('-') {
         println!("The number is negative, can't be prime.");
-        return;
+        return Ok(ExitCode::from(EXIT_REJECTED));
     }
-    let n : u128 =  match s.parse::<u128>() {
-        Ok(n) => n,
-        Err(_) => {
-            println!("{} is not a valid number", s);
-            return;
-        }
-    };
+    let n: BigRsaInt = s.parse::<BigRsaInt>().map_err(|_| format!("{} is not a valid number", s))?;
 
     println!("checking primality...");
-    println!("{} is {}", n, if is_prime_(n) { "prime" } else { "not prime" });
+    let prime: bool = is_prime_(n.clone());
+    println!("{} is {}", n, if prime { "prime" } else { "not prime" });
+    Ok(ExitCode::from(if prime { 0 } else { EXIT_REJECTED }))
 }
@@ -1,198 +1,469 @@
-#[allow(dead_code)]
+use std::{net::{TcpListener, TcpStream}, io::{Write, Read}};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use crate::fingerprint::Fingerprint;
+use crate::hash::Hash;
+use crate::known_hosts::{HostKeyPolicy, HostStatus, KnownHosts};
+use crate::padding::{oaep_decrypt, oaep_encrypt, oaep_max_len, PaddingError};
+use crate::rsa::{PrivateKey, PublicKey, RsaError, RsaInt, BigRsaInt, ContentCipher, KeyWrap, generate, generate_bits, encrypt_tab, decrypt_tab, open, seal};
+use crate::rsa_tools::{from_hex, to_hex};
 
-pub mod network {
-    use std::{net::{TcpListener, TcpStream}, io::{Write, Read}};
-    use crate::rsa::rsa::{PrivateKey, PublicKey, generate, encrypt_tab, decrypt_tab};
+/** Size of the keys generated for one OAEP or sealed connection, they are thrown away at its end */
+pub const OAEP_KEY_BITS : usize = 1024;
+/** Minimal size of the identity of the receiver, it is kept from one connection to the next */
+pub const IDENTITY_KEY_BITS : usize = 2048;
+/** Maximal size of a line (request, key or message), a longer one ends the connection */
+pub const MAX_LINE_LEN : usize = 1 << 20;
 
-    pub struct NetworkListener {
-        listener: TcpListener,
-    }
+/**
+ * How the sender encrypts its messages.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    /** One textbook RSA block per byte, with a small key */
+    Textbook,
+    /** RSA-OAEP (SHA-256) blocks written in hexadecimal */
+    Oaep,
+    /** Each message sealed with a new content key, written in base64 */
+    Seal(KeyWrap, ContentCipher),
+}
 
-    pub struct NetworkWriter {
-        stream: TcpStream,
+impl Protocol {
+    /**
+     * @return the request asking the receiver for its key.
+     */
+    fn start_(&self) -> &'static str {
+        match self {
+            Protocol::Textbook => "start",
+            Protocol::Oaep => "start-oaep",
+            Protocol::Seal(_, _) => "start-seal",
+        }
     }
+}
 
-    impl NetworkListener {
-        pub fn new(addr: &str) -> NetworkListener {
-            let listener = match TcpListener::bind(addr) {
-                Ok(listener) => listener,
-                Err(e) => panic!("Error : {}", e),
-            };
-            NetworkListener { listener }
-        }
+/**
+ * TCP receiver : it sends its public key on request
+ * and decrypts the messages of the senders, one thread per connection.
+ */
+pub struct NetworkListener {
+    listener: TcpListener,
+    identity: Option<(PublicKey<BigRsaInt>, PrivateKey<BigRsaInt>)>,     //stable OAEP keys, generated per connection when None
+}
 
-        pub fn listen(&self) {
-            for stream in self.listener.incoming() {
-                match stream {
-                    Ok(stream) => {
-                        println!("New connection: {}", stream.peer_addr().expect("unable to read addr"));
+/**
+ * One end of a connection, it sends and receives lines.
+ * The sender uses it to ask the key of the receiver and send its messages.
+ */
+pub struct NetworkWriter {
+    stream: TcpStream,
+    pending: Vec<u8>,                                       //received bytes not yet returned by read_line
+    addr: String,                                           //address of the peer, key of the known hosts
+    known_hosts: Option<(KnownHosts, HostKeyPolicy)>,       //the receiver key is not checked when None
+}
 
-                        self.handle_stream(stream);
-                    }
-                    Err(e) => {
-                        println!("Error: {}", e);
+impl NetworkListener {
+    /**
+     * Listen on addr, e.g. "127.0.0.1:1234".
+     */
+    pub fn new(addr: &str) -> Result<NetworkListener, RsaError> {
+        let listener = match TcpListener::bind(addr) {
+            Ok(listener) => listener,
+            Err(e) => return Err(RsaError::Network(format!("unable to listen on {} : {}", addr, e))),
+        };
+        Ok(NetworkListener { listener, identity: None })
+    }
+
+    /**
+     * Use the same OAEP key pair for every connection instead of generating one,
+     * so that the senders can recognize the receiver.
+     */
+    pub fn set_identity(&mut self, private: PrivateKey<BigRsaInt>) {
+        self.identity = Some((private.public_key(), private));
+    }
+
+    /**
+     * Accept the connections until the process ends.
+     */
+    pub fn listen(&self) {
+        match &self.identity {
+            Some((public, _)) => {
+                println!("Receiver fingerprint :");
+                print_fingerprint(public);
+            }
+            None => println!("No identity, new keys are generated for each connection"),
+        }
+        for stream in self.listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    match stream.peer_addr() {
+                        Ok(addr) => println!("New connection: {}", addr),
+                        Err(_) => println!("New connection"),
                     }
+
+                    self.handle_stream(stream);
+                }
+                Err(e) => {
+                    println!("Error: {}", e);
                 }
             }
         }
+    }
 
-        fn handle_stream(&self, stream: TcpStream) {
-            std::thread::spawn(move || {
-                let mut network = NetworkWriter::construct(stream);
-                let mut keys :(PublicKey<u128>, PrivateKey<u128>) = (PublicKey::construct(0, 0), PrivateKey::construct(0));
-                let mut err = 0;
-                loop {
-                    match network.read().to_lowercase().as_str() {
-                        "start" => {
-                            println!("generate keys");
-                            keys = generate(9);
-                            let public_key_str = keys.0.to_string();
-                            println!("{}", public_key_str);
-                            network.write(public_key_str.as_str());
+    fn handle_stream(&self, stream: TcpStream) {
+        let identity: Option<(PublicKey<BigRsaInt>, PrivateKey<BigRsaInt>)> = self.identity.clone();
+        std::thread::spawn(move || {
+            let mut network = NetworkWriter::construct(stream);
+            if let Err(e) = NetworkListener::session_(&mut network, identity) {
+                println!("Error: {}", e);
+            }
+            if let Err(e) = network.close() {
+                println!("Error: {}", e);
+            }
+        });
+    }
+
+    /**
+     * Answer the start requests and decrypt the messages of a sender.
+     * A malformed message is reported and skipped, the session ends on a connection error.
+     */
+    fn session_(network: &mut NetworkWriter, identity: Option<(PublicKey<BigRsaInt>, PrivateKey<BigRsaInt>)>) -> Result<(), RsaError> {
+        let mut keys: Option<(PublicKey<u128>, PrivateKey<u128>)> = None;
+        let mut oaep_keys: Option<(PublicKey<BigRsaInt>, PrivateKey<BigRsaInt>)> = None;
+        let mut sealed: bool = false;
+        loop {
+            //one request, key or message per line, a read can hold a part of one or several of them
+            //the requests are not case sensitive, the base64 messages are
+            let received: String = network.read_line()?;
+            match received.to_lowercase().as_str() {
+                "start" => {
+                    println!("generate keys");
+                    let generated: (PublicKey<u128>, PrivateKey<u128>) = generate(9)?;
+                    oaep_keys = None;
+                    let public_key_str = generated.0.to_string();
+                    println!("{}", public_key_str);
+                    print_fingerprint(&generated.0);
+                    network.write_line(public_key_str.as_str())?;
+                    keys = Some(generated);
+                }
+                request @ ("start-oaep" | "start-seal") => {
+                    let generated = match &identity {
+                        Some(identity) => identity.clone(),
+                        None => {
+                            println!("generate OAEP keys");
+                            let generated: (PublicKey<BigRsaInt>, PrivateKey<BigRsaInt>) = generate_bits(OAEP_KEY_BITS)?;
+                            print_fingerprint(&generated.0);
+                            generated
                         }
-                        received => {
-                            if !received.is_empty() {
-                                //decrypt with private key
-                                println!("received : {}", received);
-                                let split = received.split("|");
-                                let vec = NetworkListener::split_to_vec(split);
-                                let decrypted = decrypt_tab(&vec, &keys.0, &keys.1);
+                    };
+                    let public_key_str = generated.0.to_string();
+                    println!("{}", public_key_str);
+                    network.write_line(public_key_str.as_str())?;
+                    oaep_keys = Some(generated);
+                    sealed = request == "start-seal";
+                }
+                _ => {
+                    let received: &str = received.as_str();
+                    if !received.is_empty() {
+                        //decrypt with private key
+                        println!("received : {}", received);
+                        let decrypted = match (&oaep_keys, &keys) {
+                            (Some((_, private)), _) if sealed => NetworkListener::open_message(received, private),
+                            (Some((_, private)), _) => NetworkListener::oaep_decrypt_message(received, private),
+                            (None, Some((_, private))) => NetworkListener::split_to_vec(received.split("|")).map(|vec| {
+                                let decrypted = decrypt_tab(&vec, private);
                                 let mut decrypt_string = String::new();
-                                for i in 0..decrypted.len() {
-                                    decrypt_string.push(decrypted[i] as u8 as char);
+                                for c in decrypted {
+                                    decrypt_string.push(c as u8 as char);
                                 }
-                                println!("decrypted : {}", decrypt_string);
-                            }
-                            else {
-                                err += 1;
-                                if err > 10 {
-                                    println!("stop receive data");
-                                    break;
-                                }
-                            }
+                                decrypt_string
+                            }),
+                            (None, None) => Err(RsaError::Network(String::from("message received before start"))),
+                        };
+                        match decrypted {
+                            Ok(decrypt_string) => println!("decrypted : {}", decrypt_string),
+                            Err(e) => println!("Error: {}", e),
                         }
                     }
-                    std::thread::sleep(std::time::Duration::from_millis(200));
+                    else {
+                        println!("stop receive data");      //closed by the sender
+                        return Ok(());
+                    }
                 }
-
-                network.close();
-            });
+            }
         }
+    }
 
-        fn split_to_vec(split: std::str::Split<&str>) -> Vec<u128> {
-            let mut res = Vec::<u128>::new();
-            let parser = | s: &str | -> u128 {
-                match s.parse::<u128>() {
-                    Ok(x) => x,
-                    Err(e) => panic!("{}", e),
-                }
+    /**
+     * Decrypt a message made of OAEP blocks written in hexadecimal and separated by '|'.
+     */
+    fn oaep_decrypt_message(received: &str, private: &PrivateKey<BigRsaInt>) -> Result<String, RsaError> {
+        let mut decrypted: Vec<u8> = Vec::new();
+        for block in received.split('|').filter(|s| !s.is_empty()) {
+            let cipher = match from_hex(block) {
+                Some(cipher) => cipher,
+                None => return Err(RsaError::Network(format!("invalid hexadecimal block {}", block))),
             };
+            decrypted.extend_from_slice(&oaep_decrypt(&cipher, private, Hash::Sha256, b"")?);
+        }
+        Ok(String::from_utf8_lossy(&decrypted).to_string())
+    }
 
-            for s in split {
-                if !s.is_empty() {
-                    let u = parser(s);
-                    res.push(u);
-                }
+    /**
+     * Open a sealed message written in base64.
+     */
+    fn open_message(received: &str, private: &PrivateKey<BigRsaInt>) -> Result<String, RsaError> {
+        let sealed: Vec<u8> = match STANDARD.decode(received.trim()) {
+            Ok(sealed) => sealed,
+            Err(_) => return Err(RsaError::Network(String::from("the sealed message is not written in base64"))),
+        };
+        Ok(String::from_utf8_lossy(&open(&sealed, private)?).to_string())
+    }
+
+    fn split_to_vec(split: std::str::Split<&str>) -> Result<Vec<u128>, RsaError> {
+        let mut res = Vec::<u128>::new();
+        let parser = | s: &str | -> Result<u128, RsaError> {
+            match s.parse::<u128>() {
+                Ok(x) => Ok(x),
+                Err(e) => Err(RsaError::Network(format!("invalid block {} : {}", s, e))),
             }
+        };
 
-            res
+        for s in split {
+            if !s.is_empty() {
+                let u = parser(s)?;
+                res.push(u);
+            }
         }
 
+        Ok(res)
     }
 
+}
 
-    impl NetworkWriter {
-        pub fn new(addr: &str) -> NetworkWriter {
-            let stream = match TcpStream::connect(addr) {
-                Ok(stream) => stream,
-                Err(e) => panic!("Error : {}", e),
-            };
-            NetworkWriter { stream }
-        }
 
-        pub fn construct(stream : TcpStream) -> NetworkWriter {
-            NetworkWriter { stream }
+impl NetworkWriter {
+    /**
+     * Connect to the receiver listening on addr.
+     */
+    pub fn new(addr: &str) -> Result<NetworkWriter, RsaError> {
+        let stream = match TcpStream::connect(addr) {
+            Ok(stream) => stream,
+            Err(e) => return Err(RsaError::Network(format!("unable to connect to {} : {}", addr, e))),
+        };
+        Ok(NetworkWriter { stream, pending: Vec::new(), addr: addr.to_string(), known_hosts: None })
+    }
+
+    /**
+     * Wrap a connection accepted by the receiver.
+     */
+    pub fn construct(stream : TcpStream) -> NetworkWriter {
+        let addr: String = stream.peer_addr().map(|addr| addr.to_string()).unwrap_or_default();
+        NetworkWriter { stream, pending: Vec::new(), addr, known_hosts: None }
+    }
+
+    /**
+     * Check the OAEP key of the receiver against the known hosts (trust on first use).
+     * @param policy what to do when the key of the address has changed.
+     */
+    pub fn set_known_hosts(&mut self, known_hosts: KnownHosts, policy: HostKeyPolicy) {
+        self.known_hosts = Some((known_hosts, policy));
+    }
+
+    /**
+     * Send a message on its own line, the message must not contain a newline.
+     * @return the number of bytes sent.
+     */
+    pub fn write_line(&mut self, message: &str) -> Result<usize, RsaError> {
+        let line: String = format!("{}\n", message);
+        if let Err(e) = self.stream.write_all(line.as_bytes()).and_then(|_| self.stream.flush()) {
+            return Err(RsaError::Network(e.to_string()));
         }
+        println!("{} bytes sent", line.len());
+        Ok(line.len())
+    }
 
-        pub fn write(&mut self, message: &str) {
-            match self.stream.write(message.as_bytes()) {
-                Ok(size) => { println!("{} bytes sent", size); },
-                Err(e) => println!("Error : {}", e),
+    /**
+     * Read until a whole line has been received.
+     * @return the line without its newline, empty when the connection is closed.
+     */
+    pub fn read_line(&mut self) -> Result<String, RsaError> {
+        loop {
+            if let Some(end) = self.pending.iter().position(|&b| b == b'\n') {
+                let line: Vec<u8> = self.pending.drain(..=end).take(end).collect();
+                return match String::from_utf8(line) {
+                    Ok(received) => Ok(received.trim_end_matches('\r').to_string()),
+                    Err(_) => Err(RsaError::Network(String::from("the peer sent non UTF-8 data"))),
+                };
             }
-            match self.stream.flush() {
-                Ok(_) => {},
-                Err(e) => println!("Error : {}", e),
+            if self.pending.len() > MAX_LINE_LEN {
+                return Err(RsaError::Network(format!("the peer sent a line longer than {} bytes", MAX_LINE_LEN)));
             }
-        }
-
-        pub fn read(&mut self) -> String {
             let mut buf : [u8; 2048] = [0; 2048];
             match self.stream.read(&mut buf) {
-                Ok(size) => {
-                    let received = match String::from_utf8(buf[0..size].to_vec()) {
-                        Ok(received) => received,
-                        Err(e) => panic!("Error : {}", e),
-                    };
-                    received
-                }
-                Err(_) => String::new(),
+                Ok(0) => return Ok(String::new()),      //closed, an unfinished line is dropped
+                Ok(size) => self.pending.extend_from_slice(&buf[0..size]),
+                Err(e) => return Err(RsaError::Network(e.to_string())),
             }
         }
+    }
 
-        pub fn close(&mut self) {
-            match self.stream.shutdown(std::net::Shutdown::Both) {
-                Ok(_) => println!("disconnect"),
-                Err(e) => println!("Error : {e}"),
+    /**
+     * Shut the connection down, a connection already closed by the peer is not an error.
+     */
+    pub fn close(&mut self) -> Result<(), RsaError> {
+        match self.stream.shutdown(std::net::Shutdown::Both) {
+            Ok(_) => {
+                println!("disconnect");
+                Ok(())
             }
+            Err(e) if e.kind() == std::io::ErrorKind::NotConnected => Ok(()),     //already closed by the peer
+            Err(e) => Err(RsaError::Network(e.to_string())),
         }
+    }
 
-        fn parse_public_key(&self, received: &String) -> PublicKey<u128> {
-            println!("{}", received);
-
-            let parser = |i: usize| -> u128 {
-                let mut split = received.split("||");
-                match split.nth(i) {
-                    Some(res) => match res.parse::<u128>() {
-                            Ok(a) => a,
-                            Err(e) => panic!("{}", e)
-                        },
-                    None => panic!("out of bound split"),
-                }
-            };
-            let e = parser(2);
-            let n = parser(1);
-            PublicKey::construct(e, n)
-        }
+    fn parse_public_key<T>(&self, received: &str) -> Result<PublicKey<T>, RsaError>
+    where T: RsaInt + std::str::FromStr
+    {
+        println!("{}", received);
+
+        let public: PublicKey<T> = received.parse::<PublicKey<T>>()?;
+        println!("Fingerprint of the receiver key, check it with the receiver before sending :");
+        print_fingerprint(&public);
+        Ok(public)
+    }
 
-        pub fn listen(&mut self) {
-            self.write("start");
-            let mut received = self.read();
-            while received.is_empty() { received = self.read() }
+    /**
+     * Connect to the receiver and send the user input until "stop".
+     * @param protocol how the messages are encrypted, only the textbook protocol
+     * does not check the receiver key against the known hosts.
+     * The connection is closed at the end, even on error.
+     */
+    pub fn listen(&mut self, protocol: Protocol) -> Result<(), RsaError> {
+        let result: Result<(), RsaError> = self.send_messages_(protocol);
+        let closed: Result<(), RsaError> = self.close();
+        result.and(closed)
+    }
 
-            let public = self.parse_public_key(&received);
+    fn send_messages_(&mut self, protocol: Protocol) -> Result<(), RsaError> {
+        self.write_line(protocol.start_())?;
+        let received = self.read_line()?;
+        if received.is_empty() {
+            return Err(RsaError::Network(String::from("the receiver closed the connection")));
+        }
 
-            let get_input_user = || -> String {
-                let mut input = String::new();
-                let _in = std::io::stdin();
-                _in.read_line(&mut input).expect("unable to get input");
-                input
-            };
+        let get_input_user = || -> Result<String, RsaError> {
+            let mut input = String::new();
+            let _in = std::io::stdin();
+            match _in.read_line(&mut input) {
+                Ok(0) => Ok(String::from("stop")),     //end of the input
+                Ok(_) => Ok(input),
+                Err(e) => Err(RsaError::Network(format!("unable to get input : {}", e))),
+            }
+        };
+
+        if protocol != Protocol::Textbook {
+            let public: PublicKey<BigRsaInt> = self.parse_public_key(&received)?;
+            self.check_host_key_(&public)?;
+            let mut input = get_input_user()?;
+            while input.trim_end() != "stop" {
+                let message: String = match protocol {
+                    Protocol::Seal(wrap, cipher) => STANDARD.encode(seal(input.as_bytes(), &public, wrap, cipher)?),
+                    _ => NetworkWriter::oaep_encrypt_message(input.as_bytes(), &public)?,
+                };
+                self.write_line(&message)?;
 
-            let mut input = get_input_user();
-            while &input[0..&input.len() - 2] != "stop" {
+                input = get_input_user()?;
+            }
+        } else {
+            let public: PublicKey<u128> = self.parse_public_key(&received)?;
+            println!("Warning: the textbook mode does not check the key of the receiver against the known hosts,");
+            println!("its keys are new and tiny for each connection, anyone on the way can read or forge the messages");
+            let mut input = get_input_user()?;
+            while input.trim_end() != "stop" {
                 let encrypted = encrypt_tab(input.as_bytes(), &public);
                 let mut message = String::new();
                 for i in encrypted {
                     message.push_str(format!("{}|", i).as_str());
                 }
-                self.write(&message);
+                self.write_line(&message)?;
 
-                input = get_input_user();
+                input = get_input_user()?;
             }
+        }
 
-            self.close();
+        Ok(())
+    }
 
+    /**
+     * Compare the receiver key with the known hosts, the first key of an address is recorded.
+     * @return an error if the connection must be closed.
+     */
+    fn check_host_key_(&mut self, public: &PublicKey<BigRsaInt>) -> Result<(), RsaError> {
+        let (known_hosts, policy) = match &mut self.known_hosts {
+            Some((known_hosts, policy)) => (known_hosts, *policy),
+            None => return Ok(()),
+        };
+        let fingerprint: Fingerprint = public.fingerprint();
+        let record = |known_hosts: &mut KnownHosts| if let Err(e) = known_hosts.add(&self.addr, &fingerprint) {
+            println!("Error: unable to write {} : {}", known_hosts.path().display(), e);
+        };
+        match known_hosts.check(&self.addr, &fingerprint) {
+            HostStatus::Known => {
+                println!("The key of {} matches {}", self.addr, known_hosts.path().display());
+                Ok(())
+            }
+            HostStatus::Unknown => {
+                println!("First connection to {}, its key is recorded in {}", self.addr, known_hosts.path().display());
+                record(known_hosts);
+                Ok(())
+            }
+            HostStatus::Changed(known) => {
+                println!("@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@");
+                println!("@    WARNING: THE KEY OF THE RECEIVER HAS CHANGED!       @");
+                println!("@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@");
+                println!("Someone could be eavesdropping on you (man-in-the-middle attack),");
+                println!("or the receiver changed its key.");
+                println!("Recorded fingerprint of {} : {}", self.addr, known);
+                println!("Received fingerprint : {}", fingerprint);
+                match policy {
+                    HostKeyPolicy::Refuse => {
+                        println!("Remove {} from {} if the new key is expected", self.addr, known_hosts.path().display());
+                        Err(RsaError::KeyValidation(format!("the key of {} has changed, connection refused", self.addr)))
+                    }
+                    HostKeyPolicy::Warn => {
+                        println!("The new key is accepted and recorded");
+                        record(known_hosts);
+                        Ok(())
+                    }
+                }
+            }
         }
     }
 
+    /**
+     * Encrypt a message in OAEP blocks written in hexadecimal and separated by '|'.
+     */
+    fn oaep_encrypt_message(input: &[u8], public: &PublicKey<BigRsaInt>) -> Result<String, RsaError> {
+        let max_len: usize = oaep_max_len(public, Hash::Sha256);
+        if max_len == 0 {
+            return Err(RsaError::Padding(PaddingError::KeyTooSmall));
+        }
+        let mut message = String::new();
+        for chunk in input.chunks(max_len) {
+            let cipher: Vec<u8> = oaep_encrypt(chunk, public, Hash::Sha256, b"")?;
+            message.push_str(format!("{}|", to_hex(&cipher)).as_str());
+        }
+        Ok(message)
+    }
+}
+
+/**
+ * Print the fingerprint of a key in base64, hexadecimal and randomart.
+ */
+pub fn print_fingerprint<T>(key: &PublicKey<T>)
+where T: RsaInt
+{
+    let fingerprint: Fingerprint = key.fingerprint();
+    println!("{}", fingerprint);
+    println!("{}", fingerprint.to_hex());
+    print!("{}", fingerprint.randomart());
 }
@@ -0,0 +1,337 @@
+use aes::cipher::{KeyIvInit, StreamCipher};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use rand::Rng;
+
+use crate::encoding::{pem_decode, pem_encode, EncodingError};
+use crate::rsa::{PrivateKey, PublicKey, RsaInt};
+use crate::rsa_tools::{byte_length, from_bytes, to_bytes};
+
+type Aes256Ctr = ctr::Ctr128BE<aes::Aes256>;
+
+const KEY_TYPE : &str = "ssh-rsa";
+const AUTH_MAGIC : &[u8] = b"openssh-key-v1\0";
+const PEM_LABEL : &str = "OPENSSH PRIVATE KEY";
+const CIPHER : &str = "aes256-ctr";
+const KDF : &str = "bcrypt";
+const KDF_ROUNDS : u32 = 16;     //ssh-keygen default
+const SALT_LEN : usize = 16;
+
+/**
+ * Write a public key as an authorized_keys line: `ssh-rsa <base64> <comment>`
+ */
+pub fn public_key_to_openssh<T>(key: &PublicKey<T>, comment: &str) -> String
+where T: RsaInt
+{
+    let line: String = format!("{} {}", KEY_TYPE, STANDARD.encode(public_key_blob(key)));
+    if comment.is_empty() {
+        line
+    } else {
+        format!("{} {}", line, comment)
+    }
+}
+
+/**
+ * Read an authorized_keys line.
+ * @return the public key and its comment.
+ */
+pub fn public_key_from_openssh<T>(line: &str) -> Result<(PublicKey<T>, String), EncodingError>
+where T: RsaInt
+{
+    let mut fields = line.split_whitespace();
+    match fields.next() {
+        Some(KEY_TYPE) => {},
+        Some(_) => return Err(EncodingError::UnsupportedAlgorithm),
+        None => return Err(EncodingError::InvalidDer),
+    }
+    let blob: Vec<u8> = match fields.next().map(|b| STANDARD.decode(b)) {
+        Some(Ok(blob)) => blob,
+        _ => return Err(EncodingError::InvalidDer),
+    };
+    let comment: String = fields.collect::<Vec<&str>>().join(" ");
+    Ok((public_key_from_blob(&blob)?, comment))
+}
+
+/**
+ * Wire encoding of a public key: string "ssh-rsa", mpint e, mpint n.
+ */
+pub fn public_key_blob<T>(key: &PublicKey<T>) -> Vec<u8>
+where T: RsaInt
+{
+    let mut blob: Vec<u8> = Vec::new();
+    put_string_(&mut blob, KEY_TYPE.as_bytes());
+    put_mpint_(&mut blob, key.e());
+    put_mpint_(&mut blob, key.n());
+    blob
+}
+
+/**
+ * Read the wire encoding of a public key.
+ */
+pub fn public_key_from_blob<T>(blob: &[u8]) -> Result<PublicKey<T>, EncodingError>
+where T: RsaInt
+{
+    let mut reader: SshReader = SshReader { data: blob };
+    reader.read_key_type()?;
+    let e: T = reader.read_mpint()?;
+    let n: T = reader.read_mpint()?;
+    reader.finish()?;
+    let public: PublicKey<T> = PublicKey::construct(e, n);
+    if !public.is_valid() {
+        return Err(EncodingError::InvalidKey);
+    }
+    Ok(public)
+}
+
+/**
+ * Write a private key in the openssh-key-v1 container.
+ * With a passphrase, the private section is encrypted with aes256-ctr
+ * and a key derived with bcrypt_pbkdf, as ssh-keygen does.
+ */
+pub fn private_key_to_openssh<T>(key: &PrivateKey<T>, comment: &str, passphrase: Option<&str>) -> Result<String, EncodingError>
+where T: RsaInt
+{
+    let (p, q, qinv) = match (key.p(), key.q(), key.qinv()) {
+        (Some(p), Some(q), Some(qinv)) => (p, q, qinv),
+        _ => return Err(EncodingError::MissingCrtComponents),
+    };
+    let mut rng = rand::thread_rng();
+
+    let mut private: Vec<u8> = Vec::new();
+    let check: u32 = rng.gen();
+    private.extend_from_slice(&check.to_be_bytes());
+    private.extend_from_slice(&check.to_be_bytes());
+    put_string_(&mut private, KEY_TYPE.as_bytes());
+    put_mpint_(&mut private, key.n());
+    put_mpint_(&mut private, key.e());
+    put_mpint_(&mut private, key.d());
+    put_mpint_(&mut private, qinv);
+    put_mpint_(&mut private, p);
+    put_mpint_(&mut private, q);
+    put_string_(&mut private, comment.as_bytes());
+    let block_size: usize = if passphrase.is_some() { 16 } else { 8 };
+    let mut pad: u8 = 1;
+    while !private.len().is_multiple_of(block_size) {
+        private.push(pad);
+        pad += 1;
+    }
+
+    let mut container: Vec<u8> = AUTH_MAGIC.to_vec();
+    match passphrase {
+        Some(passphrase) => {
+            let mut salt: Vec<u8> = vec![0; SALT_LEN];
+            rng.fill(&mut salt[..]);
+            let mut options: Vec<u8> = Vec::new();
+            put_string_(&mut options, &salt);
+            options.extend_from_slice(&KDF_ROUNDS.to_be_bytes());
+
+            put_string_(&mut container, CIPHER.as_bytes());
+            put_string_(&mut container, KDF.as_bytes());
+            put_string_(&mut container, &options);
+            aes256_ctr_(&mut private, passphrase, &salt, KDF_ROUNDS)?;
+        }
+        None => {
+            put_string_(&mut container, b"none");
+            put_string_(&mut container, b"none");
+            put_string_(&mut container, b"");
+        }
+    }
+    container.extend_from_slice(&1u32.to_be_bytes());      //number of keys
+    put_string_(&mut container, &public_key_blob(&key.public_key()));
+    put_string_(&mut container, &private);
+
+    Ok(pem_encode(PEM_LABEL, &container))
+}
+
+/**
+ * Read a private key from the openssh-key-v1 container.
+ * @return the private key and its comment.
+ */
+pub fn private_key_from_openssh<T>(pem: &str, passphrase: Option<&str>) -> Result<(PrivateKey<T>, String), EncodingError>
+where T: RsaInt
+{
+    let (label, container) = pem_decode(pem)?;
+    if label != PEM_LABEL {
+        return Err(EncodingError::UnexpectedLabel(label));
+    }
+    if !container.starts_with(AUTH_MAGIC) {
+        return Err(EncodingError::InvalidDer);
+    }
+    let mut reader: SshReader = SshReader { data: &container[AUTH_MAGIC.len()..] };
+    let cipher: Vec<u8> = reader.read_string()?.to_vec();
+    let kdf: Vec<u8> = reader.read_string()?.to_vec();
+    let options: Vec<u8> = reader.read_string()?.to_vec();
+    if reader.read_u32()? != 1 {
+        return Err(EncodingError::InvalidDer);
+    }
+    let public: PublicKey<T> = public_key_from_blob(reader.read_string()?)?;
+    let mut private: Vec<u8> = reader.read_string()?.to_vec();
+    reader.finish()?;
+
+    match (cipher.as_slice(), kdf.as_slice()) {
+        (b"none", b"none") => {},
+        (b"aes256-ctr", b"bcrypt") => {
+            let passphrase: &str = passphrase.ok_or(EncodingError::MissingPassphrase)?;
+            let mut options: SshReader = SshReader { data: &options };
+            let salt: Vec<u8> = options.read_string()?.to_vec();
+            let rounds: u32 = options.read_u32()?;
+            options.finish()?;
+            if !private.len().is_multiple_of(16) {
+                return Err(EncodingError::InvalidDer);
+            }
+            aes256_ctr_(&mut private, passphrase, &salt, rounds)?;
+        }
+        _ => return Err(EncodingError::UnsupportedCipher(format!("{}/{}",
+            String::from_utf8_lossy(&cipher), String::from_utf8_lossy(&kdf)))),
+    }
+
+    let mut reader: SshReader = SshReader { data: &private };
+    if reader.read_u32()? != reader.read_u32()? {
+        return Err(EncodingError::WrongPassphrase);
+    }
+    reader.read_key_type()?;
+    let n: T = reader.read_mpint()?;
+    let e: T = reader.read_mpint()?;
+    let d: T = reader.read_mpint()?;
+    reader.read_mpint::<T>()?;      //qInv is recomputed
+    let p: T = reader.read_mpint()?;
+    let q: T = reader.read_mpint()?;
+    let comment: String = String::from_utf8_lossy(reader.read_string()?).to_string();
+
+    if n != public.n() || e != public.e() || p <= T::one() || q <= T::one() || p == q || p.checked_mul(&q).as_ref() != Some(&n) {
+        return Err(EncodingError::InvalidKey);
+    }
+    match PrivateKey::construct_crt(p, q, e, d) {
+        Ok(private) => Ok((private, comment)),
+        Err(_) => Err(EncodingError::InvalidKey),
+    }
+}
+
+/**
+ * Encrypt or decrypt data in place with aes256-ctr,
+ * the key and the iv are derived from the passphrase with bcrypt_pbkdf.
+ */
+fn aes256_ctr_(data: &mut [u8], passphrase: &str, salt: &[u8], rounds: u32) -> Result<(), EncodingError> {
+    let mut key_iv: [u8; 48] = [0; 48];
+    if bcrypt_pbkdf::bcrypt_pbkdf(passphrase, salt, rounds, &mut key_iv).is_err() {
+        return Err(EncodingError::InvalidDer);
+    }
+    let mut cipher = Aes256Ctr::new(key_iv[..32].into(), key_iv[32..].into());
+    cipher.apply_keystream(data);
+    Ok(())
+}
+
+fn put_string_(out: &mut Vec<u8>, data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    out.extend_from_slice(data);
+}
+
+fn put_mpint_<T>(out: &mut Vec<u8>, n: T)
+where T: RsaInt
+{
+    let mut bytes: Vec<u8> = to_bytes(n.clone(), byte_length(n)).unwrap();
+    if !bytes.is_empty() && bytes[0] & 0x80 != 0 {
+        bytes.insert(0, 0x00);
+    }
+    put_string_(out, &bytes);
+}
+
+/**
+ * Sequential reader of the SSH wire encoding (RFC 4251).
+ */
+struct SshReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> SshReader<'a> {
+    fn read_u32(&mut self) -> Result<u32, EncodingError> {
+        if self.data.len() < 4 {
+            return Err(EncodingError::InvalidDer);
+        }
+        let (value, rest) = self.data.split_at(4);
+        self.data = rest;
+        Ok(u32::from_be_bytes([value[0], value[1], value[2], value[3]]))
+    }
+
+    fn read_string(&mut self) -> Result<&'a [u8], EncodingError> {
+        let length: usize = self.read_u32()? as usize;
+        if self.data.len() < length {
+            return Err(EncodingError::InvalidDer);
+        }
+        let (value, rest) = self.data.split_at(length);
+        self.data = rest;
+        Ok(value)
+    }
+
+    fn read_mpint<T>(&mut self) -> Result<T, EncodingError>
+    where T: RsaInt
+    {
+        let mut bytes: &[u8] = self.read_string()?;
+        if !bytes.is_empty() && bytes[0] & 0x80 != 0 {
+            return Err(EncodingError::InvalidDer);      //negative numbers are not used by the keys
+        }
+        while !bytes.is_empty() && bytes[0] == 0x00 {
+            bytes = &bytes[1..];
+        }
+        let n: T = from_bytes(bytes);
+        if to_bytes(n.clone(), bytes.len()).as_deref() != Some(bytes) {
+            return Err(EncodingError::IntegerTooBig);
+        }
+        Ok(n)
+    }
+
+    fn read_key_type(&mut self) -> Result<(), EncodingError> {
+        match self.read_string()? {
+            b"ssh-rsa" => Ok(()),
+            _ => Err(EncodingError::UnsupportedAlgorithm),
+        }
+    }
+
+    fn finish(&self) -> Result<(), EncodingError> {
+        if self.data.is_empty() {
+            Ok(())
+        } else {
+            Err(EncodingError::InvalidDer)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rsa::{generate_bits, BigRsaInt};
+
+    //wire encoding of a public key, built by hand to write degenerate keys
+    fn blob_(e: u128, n: u128) -> Vec<u8> {
+        let mut blob: Vec<u8> = Vec::new();
+        for field in [b"ssh-rsa".to_vec(), mpint_(e), mpint_(n)] {
+            blob.extend_from_slice(&(field.len() as u32).to_be_bytes());
+            blob.extend_from_slice(&field);
+        }
+        blob
+    }
+
+    fn mpint_(x: u128) -> Vec<u8> {
+        let mut bytes: Vec<u8> = x.to_be_bytes().iter().copied().skip_while(|&b| b == 0).collect();
+        if bytes.first().is_some_and(|&b| b & 0x80 != 0) {
+            bytes.insert(0, 0);
+        }
+        bytes
+    }
+
+    #[test]
+    fn keys_round_trip() {
+        let (public, private) = generate_bits::<BigRsaInt>(512).unwrap();
+        let (decoded, comment): (PublicKey<BigRsaInt>, String) = public_key_from_openssh(&public_key_to_openssh(&public, "me@host")).unwrap();
+        assert_eq!((decoded.n(), decoded.e(), comment.as_str()), (public.n(), public.e(), "me@host"));
+        let (decoded, comment): (PrivateKey<BigRsaInt>, String) = private_key_from_openssh(&private_key_to_openssh(&private, "me@host", None).unwrap(), None).unwrap();
+        assert_eq!((decoded.n(), decoded.d(), decoded.p(), comment.as_str()), (private.n(), private.d(), private.p(), "me@host"));
+    }
+
+    #[test]
+    fn degenerate_public_keys_are_rejected() {
+        for (e, n) in [(3, 0), (3, 1), (0, 3233), (1, 3233), (4, 3233), (17, 3234), (3233, 3233)] {
+            assert_eq!(public_key_from_blob::<u128>(&blob_(e, n)).err(), Some(EncodingError::InvalidKey), "n = {}, e = {}", n, e);
+        }
+        assert!(public_key_from_blob::<u128>(&blob_(17, 3233)).is_ok());
+    }
+}
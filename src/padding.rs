@@ -0,0 +1,231 @@
+use rand::Rng;
+
+use crate::hash::Hash;
+use crate::rsa::{PrivateKey, PublicKey, RsaInt};
+use crate::rsa_tools::{byte_length, from_bytes, to_bytes};
+
+//minimal number of random non-zero bytes in a PKCS#1 v1.5 encryption block
+const PKCS1_MIN_PADDING : usize = 8;
+
+/**
+ * Errors returned by the padding schemes.
+ * It converts into RsaError::Padding.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaddingError {
+    /** The message does not fit in the key */
+    MessageTooLong,
+    /** The key is too small for the padding scheme */
+    KeyTooSmall,
+    /** The cipher does not have the size of the modulus */
+    CipherLength,
+    /** The cipher is not lower than the modulus */
+    CipherTooBig,
+    /** The decrypted block is malformed */
+    InvalidPadding,
+    /** The signature does not match the message */
+    InvalidSignature,
+}
+
+impl std::fmt::Display for PaddingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PaddingError::MessageTooLong => write!(f, "message too long for the key"),
+            PaddingError::KeyTooSmall => write!(f, "key too small for the padding scheme"),
+            PaddingError::CipherLength => write!(f, "cipher length does not match the key size"),
+            PaddingError::CipherTooBig => write!(f, "cipher out of the modulus range"),
+            PaddingError::InvalidPadding => write!(f, "invalid padding"),
+            PaddingError::InvalidSignature => write!(f, "invalid signature"),
+        }
+    }
+}
+
+impl std::error::Error for PaddingError {}
+
+/**
+ * RSAES-PKCS1-v1_5 encryption.
+ * The message is padded in a block EB = 00 || 02 || PS || 00 || M
+ * of the size of the modulus, where PS are at least 8 random non-zero bytes.
+ *
+ * @param message the bytes to encrypt, at most k - 11 bytes for a k bytes modulus.
+ * @param key the public key use to encrypt the message.
+ *
+ * @return the encrypted block on k bytes.
+ */
+pub fn pkcs1_encrypt<T>(message: &[u8], key: &PublicKey<T>) -> Result<Vec<u8>, PaddingError>
+where T: RsaInt
+{
+    let k: usize = byte_length(key.n());
+    if message.len() + PKCS1_MIN_PADDING + 3 > k {
+        return Err(PaddingError::MessageTooLong);
+    }
+
+    let mut rng = rand::thread_rng();
+    let mut block: Vec<u8> = Vec::with_capacity(k);
+    block.push(0x00);
+    block.push(0x02);
+    for _ in 0..(k - message.len() - 3) {
+        block.push(rng.gen_range(1..=u8::MAX));
+    }
+    block.push(0x00);
+    block.extend_from_slice(message);
+
+    let cipher: T = key.encrypt(from_bytes(&block));
+    to_bytes(cipher, k).ok_or(PaddingError::CipherTooBig)
+}
+
+/**
+ * RSAES-PKCS1-v1_5 decryption.
+ *
+ * @param cipher the encrypted block, it must have the size of the modulus.
+ * @param key the private key use to decrypt the message.
+ *
+ * @return the decrypted message.
+ */
+pub fn pkcs1_decrypt<T>(cipher: &[u8], key: &PrivateKey<T>) -> Result<Vec<u8>, PaddingError>
+where T: RsaInt
+{
+    let block: Vec<u8> = decrypt_block_(cipher, key)?;
+    if block[0] != 0x00 || block[1] != 0x02 {
+        return Err(PaddingError::InvalidPadding);
+    }
+    let separator: usize = match block[2..].iter().position(|b| *b == 0x00) {
+        Some(i) => i + 2,
+        None => return Err(PaddingError::InvalidPadding),
+    };
+    if separator - 2 < PKCS1_MIN_PADDING {
+        return Err(PaddingError::InvalidPadding);
+    }
+
+    Ok(block[separator + 1..].to_vec())
+}
+
+/**
+ * RSAES-OAEP encryption (RFC 8017).
+ * The message is padded with a random seed so two encryptions
+ * of the same message give different ciphers.
+ *
+ * @param message the bytes to encrypt, at most k - 2 * hLen - 2 bytes for a k bytes modulus.
+ * @param key the public key use to encrypt the message.
+ * @param hash the hash function, also used by MGF1.
+ * @param label an optional label associated to the message (can be empty).
+ *
+ * @return the encrypted block on k bytes.
+ */
+pub fn oaep_encrypt<T>(message: &[u8], key: &PublicKey<T>, hash: Hash, label: &[u8]) -> Result<Vec<u8>, PaddingError>
+where T: RsaInt
+{
+    let k: usize = byte_length(key.n());
+    let h_len: usize = hash.output_len();
+    if k < 2 * h_len + 2 {
+        return Err(PaddingError::KeyTooSmall);
+    }
+    if message.len() > k - 2 * h_len - 2 {
+        return Err(PaddingError::MessageTooLong);
+    }
+
+    //DB = lHash || PS || 01 || M
+    let mut db: Vec<u8> = hash.digest(label);
+    db.resize(k - message.len() - h_len - 2, 0x00);
+    db.push(0x01);
+    db.extend_from_slice(message);
+
+    let mut seed: Vec<u8> = vec![0; h_len];
+    rand::thread_rng().fill(&mut seed[..]);
+
+    xor_(&mut db, &mgf1(&seed, k - h_len - 1, hash));
+    xor_(&mut seed, &mgf1(&db, h_len, hash));
+
+    //EM = 00 || maskedSeed || maskedDB
+    let mut block: Vec<u8> = Vec::with_capacity(k);
+    block.push(0x00);
+    block.extend_from_slice(&seed);
+    block.extend_from_slice(&db);
+
+    let cipher: T = key.encrypt(from_bytes(&block));
+    to_bytes(cipher, k).ok_or(PaddingError::CipherTooBig)
+}
+
+/**
+ * @return the maximal size of a message encrypted with RSAES-OAEP.
+ */
+pub fn oaep_max_len<T>(key: &PublicKey<T>, hash: Hash) -> usize
+where T: RsaInt
+{
+    byte_length(key.n()).saturating_sub(2 * hash.output_len() + 2)
+}
+
+/**
+ * RSAES-OAEP decryption (RFC 8017).
+ *
+ * @param cipher the encrypted block, it must have the size of the modulus.
+ * @param key the private key use to decrypt the message.
+ * @param hash the hash function used to encrypt the message.
+ * @param label the label used to encrypt the message.
+ *
+ * @return the decrypted message.
+ */
+pub fn oaep_decrypt<T>(cipher: &[u8], key: &PrivateKey<T>, hash: Hash, label: &[u8]) -> Result<Vec<u8>, PaddingError>
+where T: RsaInt
+{
+    let k: usize = byte_length(key.n());
+    let h_len: usize = hash.output_len();
+    if k < 2 * h_len + 2 {
+        return Err(PaddingError::KeyTooSmall);
+    }
+    let block: Vec<u8> = decrypt_block_(cipher, key)?;
+
+    let mut seed: Vec<u8> = block[1..h_len + 1].to_vec();
+    let mut db: Vec<u8> = block[h_len + 1..].to_vec();
+    xor_(&mut seed, &mgf1(&db, h_len, hash));
+    xor_(&mut db, &mgf1(&seed, k - h_len - 1, hash));
+
+    let separator: Option<usize> = db[h_len..].iter().position(|b| *b != 0x00).map(|i| i + h_len);
+    let valid: bool = block[0] == 0x00
+        && db[..h_len] == hash.digest(label)[..]
+        && separator.map(|i| db[i] == 0x01).unwrap_or(false);
+    match separator {
+        Some(i) if valid => Ok(db[i + 1..].to_vec()),
+        _ => Err(PaddingError::InvalidPadding),
+    }
+}
+
+/**
+ * Mask generation function MGF1 (RFC 8017).
+ * @return len bytes derived from the seed.
+ */
+pub fn mgf1(seed: &[u8], len: usize, hash: Hash) -> Vec<u8> {
+    let mut mask: Vec<u8> = Vec::with_capacity(len + hash.output_len());
+    let mut counter: u32 = 0;
+    while mask.len() < len {
+        let mut data: Vec<u8> = seed.to_vec();
+        data.extend_from_slice(&counter.to_be_bytes());
+        mask.extend_from_slice(&hash.digest(&data));
+        counter += 1;
+    }
+    mask.truncate(len);
+    mask
+}
+
+fn xor_(data: &mut [u8], mask: &[u8]) {
+    for (byte, m) in data.iter_mut().zip(mask) {
+        *byte ^= m;
+    }
+}
+
+/**
+ * Decrypt a block with the RSA primitive and write it on k bytes.
+ */
+fn decrypt_block_<T>(cipher: &[u8], key: &PrivateKey<T>) -> Result<Vec<u8>, PaddingError>
+where T: RsaInt
+{
+    let k: usize = byte_length(key.n());
+    if cipher.len() != k || k < PKCS1_MIN_PADDING + 3 {
+        return Err(PaddingError::CipherLength);
+    }
+    let c: T = from_bytes(cipher);
+    if c >= key.n() {
+        return Err(PaddingError::CipherTooBig);
+    }
+    to_bytes(key.decrypt(c), k).ok_or(PaddingError::InvalidPadding)
+}
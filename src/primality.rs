@@ -1,63 +1,101 @@
-#[allow(dead_code)]
-pub mod primality {
-    use rand::Rng;
-
-    use crate::{tools::rsa_tools::power_modulo, rsa::rsa::RsaInt};
-
-    const K : u8 = 7;
-
-    /**
-     * Generics implementation of the Miller-Rabin test.
-     * @param n the number to test.
-     * @return true if n is prime, false otherwise.
-     */
-    pub fn is_prime_<T>(n: T) -> bool
-    where T: RsaInt
-     {
-        let two: T = T::one() + T::one();
-        let mut rng: rand::rngs::ThreadRng = rand::thread_rng();
-
-        for _ in 0..K {
-            let a:T= rng.gen_range(two ..= n - two);
-            if miller_rabin_test_(n, a) {
-                return false;
-            }
+use rand::Rng;
+
+use crate::{rsa_tools::{power_modulo, Montgomery}, rsa::RsaInt};
+
+const K : u8 = 7;
+
+/**
+ * Generics implementation of the Miller-Rabin test.
+ * @param n the number to test.
+ * @return true if n is prime, false otherwise.
+ */
+pub fn is_prime_<T>(n: T) -> bool
+where T: RsaInt
+ {
+    let two: T = T::one() + T::one();
+    if n < two.clone() + two.clone() {     //0, 1, 2 and 3 have no witness range
+        return n > T::one();
+    }
+    if n.clone() & T::one() == T::zero() {
+        return false;
+    }
+    let mut rng: rand::rngs::ThreadRng = rand::thread_rng();
+    let montgomery: Option<Montgomery<T>> = Montgomery::new(n.clone());
+
+    for _ in 0..K {
+        let a:T= rng.gen_range(two.clone() ..= n.clone() - two.clone());
+        let composite = match &montgomery {
+            Some(montgomery) => montgomery_miller_rabin_test_(montgomery, a),
+            None => miller_rabin_test_(n.clone(), a),
+        };
+        if composite {
+            return false;
         }
-        true
     }
+    true
+}
 
-    fn miller_rabin_test_<T>(n: T, a: T) -> bool
-    where T: RsaInt
-    {
-        let tab : (T, T) = s_and_d_(n);
-        let s : T = tab.0;
-        let d : T = tab.1;
-        let mut x: T = power_modulo::<T>(a, d, n);
+/**
+ * Miller-Rabin round made in the Montgomery form of n,
+ * the context is shared between all the rounds.
+ * @return true if a is a witness that n is composite.
+ */
+fn montgomery_miller_rabin_test_<T>(montgomery: &Montgomery<T>, a: T) -> bool
+where T: RsaInt
+{
+    let n: T = montgomery.modulo();
+    let (s, d): (T, T) = s_and_d_(n.clone());
+    let one: T = montgomery.one();
+    let minus_one: T = n - one.clone();       //n - 1 in Montgomery form
+    let mut x: T = montgomery.pow(montgomery.to_montgomery(a), d);
+
+    if x == one || x == minus_one {
+        return false;
+    }
 
-        if x == T::one() || x == n - T::one() {
+    let stop = if s > T::zero() { s - T::one() } else { T::zero() };
+    for _ in num::iter::range(T::zero(), stop) {
+        x = montgomery.mul(x.clone(), x);
+        if x == minus_one {
             return false;
         }
+    }
 
-        let stop = if s > T::zero() { s - T::one() } else { T::zero() };
-        for _ in num::iter::range(T::zero(), stop) {
-            x = power_modulo::<T>(x, T::one() + T::one(), n);
-            if x == n - T::one() {
-                return false;
-            }
-        }
+    true
+}
 
-        true
+fn miller_rabin_test_<T>(n: T, a: T) -> bool
+where T: RsaInt
+{
+    let tab : (T, T) = s_and_d_(n.clone());
+    let s : T = tab.0;
+    let d : T = tab.1;
+    let n_minus_one: T = n.clone() - T::one();
+    let mut x: T = power_modulo::<T>(a, d, n.clone());
+
+    if x == T::one() || x == n_minus_one {
+        return false;
     }
 
-    fn s_and_d_<T>(n: T) -> (T, T)
-    where T: RsaInt
-    {
-        let mut d: T = n - T::one();
-        let mut s: T = T::zero();
-        while d & T::one() == T::zero() {
-            d = d >> 1u8;
-            s = s + T::one();
+    let stop = if s > T::zero() { s - T::one() } else { T::zero() };
+    for _ in num::iter::range(T::zero(), stop) {
+        x = power_modulo::<T>(x, T::one() + T::one(), n.clone());
+        if x == n_minus_one {
+            return false;
         }
-        (s, d)
     }
+
+    true
+}
+
+fn s_and_d_<T>(n: T) -> (T, T)
+where T: RsaInt
+{
+    let mut d: T = n - T::one();
+    let mut s: T = T::zero();
+    while d.clone() & T::one() == T::zero() {
+        d = d >> 1u8;
+        s = s + T::one();
+    }
+    (s, d)
 }
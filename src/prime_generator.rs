@@ -1,71 +1,145 @@
-#[allow(dead_code)]
-pub mod gen {
-    use rand::Rng;
-
-    use crate::{primality::primality::is_prime_, rsa::rsa::RsaInt};
-
-    /**
-     * Generate a random prime number of n numbers.
-     */
-    pub fn generator<T>(n: u8) -> T
-    where T: RsaInt
-    {
-        let mut rng: T = random_n_number_(n);
-        while (rng & T::one()) == T::zero() || !is_prime_(rng) {
-            rng = rng + T::one();
-        }
+use rand::Rng;
+
+use crate::{primality::is_prime_, rsa::{RsaError, RsaInt}};
+
+/**
+ * Generate a random prime number of n numbers.
+ */
+pub fn generator<T>(n: u16) -> T
+where T: RsaInt
+{
+    let mut rng: T = random_n_number_(n);
+    while (rng.clone() & T::one()) == T::zero() || !is_prime_(rng.clone()) {
+        rng = rng + T::one();
+    }
 
-        rng
+    rng
+}
+
+/**
+ * Generate a random prime number of exactly n bits.
+ * The two most significant bits are set so the product
+ * of two such primes has exactly 2n bits.
+ * @return an error if n is lower than 2 or too big for T.
+ */
+pub fn generator_bits<T>(n: usize) -> Result<T, RsaError>
+where T: RsaInt
+{
+    if n < 2 {
+        return Err(RsaError::Math(String::from("a prime number needs at least 2 bits")));
+    }
+    let mut rng = rand::thread_rng();
+    let two: T = T::one() + T::one();
+    //2^(n - 1) by checked doublings, a shift beyond the width of a primitive type would panic
+    let mut top: T = T::one();
+    for _ in 1..n {
+        top = match top.checked_mul(&two) {
+            Some(top) => top,
+            None => return Err(RsaError::Math(format!("a prime number of {} bits does not fit in the integer type", n))),
+        };
     }
+    let low: T = top.clone() + (top.clone() >> 1usize);     // 0b11000...0
+    let high: T = top.clone() + (top - T::one());           // 0b11111...1
 
-    pub fn generator_min<T>(min : T) -> T
-    where T : RsaInt
-    {
-        let mut rng = rand::thread_rng();
-        let twomin = min << 1u8; // min * 2
-        let mut n: T = rng.gen_range(min..=twomin);
-
-        while n & T::one() == T::zero() || !is_prime_(n) {
-            n = n + T::one();
-            if n > twomin {
-                n = rng.gen_range(min..=twomin);
+    loop {
+        let mut candidate: T = rng.gen_range(low.clone()..=high.clone());
+        if candidate.clone() & T::one() == T::zero() {
+            candidate = candidate + T::one();
+        }
+        while !is_prime_(candidate.clone()) {
+            if candidate > high.clone() - two.clone() {
+                break;
             }
+            candidate = candidate + two.clone();
+        }
+        if is_prime_(candidate.clone()) {
+            return Ok(candidate);
         }
+    }
+}
 
-        n
+/**
+ * Generate a random prime number between min and 2 * min.
+ */
+pub fn generator_min<T>(min : T) -> T
+where T : RsaInt
+{
+    let mut rng = rand::thread_rng();
+    let twomin = min.clone() << 1u8; // min * 2
+    let mut n: T = rng.gen_range(min.clone()..=twomin.clone());
+
+    while n.clone() & T::one() == T::zero() || !is_prime_(n.clone()) {
+        n = n + T::one();
+        if n > twomin {
+            n = rng.gen_range(min.clone()..=twomin.clone());
+        }
     }
 
-    pub fn generator_max<T>(max: T) -> T
-    where T: RsaInt
-    {
-        let mut rng = rand::thread_rng();
-        let halfmax = max >> 1u8; // max / 2;
-        let mut n: T = rng.gen_range(halfmax..max);
-
-        while n & T::one() == T::zero() || !is_prime_(n) {
-            n = n + T::one();
-            if n > max {
-                n = rng.gen_range(halfmax..max);
-            }
+    n
+}
+
+/**
+ * Generate a random prime number between max / 2 and max.
+ */
+pub fn generator_max<T>(max: T) -> T
+where T: RsaInt
+{
+    let mut rng = rand::thread_rng();
+    let halfmax = max.clone() >> 1u8; // max / 2;
+    let mut n: T = rng.gen_range(halfmax.clone()..max.clone());
+
+    while n.clone() & T::one() == T::zero() || !is_prime_(n.clone()) {
+        n = n + T::one();
+        if n > max {
+            n = rng.gen_range(halfmax.clone()..max.clone());
         }
+    }
+
+    n
+}
+
+/**
+ * Generate a random number of n numbers.
+ * The most significant digit is never 0.
+ */
+fn random_n_number_<T>(n: u16) -> T
+where T: RsaInt
+{
+    let mut number : T = T::zero();
+    let mut rng = rand::thread_rng();
+    let ten: T = T::from(10);              //redefine 10 as a T type
 
-        n
+    for i in 0..n {
+        let min: T = if i + 1 == n { T::one() } else { T::zero() };
+        let a: T = rng.gen_range(min..ten.clone());
+        number = number + (a * ten.clone().pow(i as u32));
     }
+    number
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    /**
-     * Generate a random number of n numbers.
-     */
-    fn random_n_number_<T>(n: u8) -> T
-    where T: RsaInt
-    {
-        let mut number : T = T::zero();
-        let mut rng = rand::thread_rng();
-        let ten: T = T::from(10);              //redefine 10 as a T type
-
-        for i in 0..n {
-            let a: T = rng.gen_range(T::zero()..ten);
-            number = number + (a * ten.pow(i as u32));
+    #[test]
+    fn generator_bits_sets_the_two_top_bits() {
+        for n in [2, 3, 8, 33, 64] {
+            let p: u64 = generator_bits(n).unwrap();
+            assert_eq!(64 - p.leading_zeros() as usize, n);
+            assert!(n < 2 || p >> (n - 2) == 0b11);
         }
-        number
+    }
+
+    #[test]
+    fn generator_bits_fills_the_integer_type() {
+        let p: u128 = generator_bits(128).unwrap();
+        assert_eq!(p.leading_zeros(), 0);
+    }
+
+    #[test]
+    fn generator_bits_rejects_a_size_wider_than_the_type() {
+        assert!(generator_bits::<u128>(129).is_err());
+        assert!(generator_bits::<u128>(300).is_err());
+        assert!(generator_bits::<u64>(1).is_err());
     }
 }
@@ -0,0 +1,611 @@
+use crate::rsa::{RsaError, RsaInt};
+
+/**
+ * Algorithm used to compute a modular exponentiation.
+ * Binary is the square and multiply algorithm,
+ * FixedWindow(k) is the 2^k-ary algorithm with a table of all the powers up to 2^k - 1,
+ * SlidingWindow(k) only precomputes the odd powers up to 2^k - 1,
+ * Auto chooses the window size from the exponent size.
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Exponentiation {
+    /** Window size chosen from the exponent size */
+    Auto,
+    /** Square and multiply */
+    Binary,
+    /** 2^k-ary with a window of k bits */
+    FixedWindow(usize),
+    /** Sliding window of at most k bits */
+    SlidingWindow(usize),
+}
+
+/**
+ * Modular exponentiation by square algorithm.
+ * This function return the Modular exponentiation in a O(log(n)) complexity.
+ * It can takes all primivites integers and big integers.
+ * When the modulo is odd and small enough for T, the Montgomery form is used.
+ * The exponentiation algorithm is chosen from the exponent size.
+ *
+ * @param nb the base.
+ * @param pow the exponent.
+ * @param modulo the modulo.
+ *
+ * @return nb^pow % modulo
+ */
+pub fn power_modulo<T>(nb: T, pow :T, modulo: T) -> T
+where T: RsaInt
+{
+    power_modulo_(nb, pow, modulo, Exponentiation::Auto)
+}
+
+/**
+ * Modular exponentiation with the given exponentiation algorithm.
+ *
+ * @return nb^pow % modulo, an error if the window size is not between 1 and 8.
+ */
+pub fn power_modulo_with<T>(nb: T, pow :T, modulo: T, method: Exponentiation) -> Result<T, RsaError>
+where T: RsaInt
+{
+    check_method_(method)?;
+    Ok(power_modulo_(nb, pow, modulo, method))
+}
+
+fn power_modulo_<T>(nb: T, pow :T, modulo: T, method: Exponentiation) -> T
+where T: RsaInt
+{
+    if let Some(montgomery) = Montgomery::new(modulo.clone()) {
+        return montgomery.to_normal(exponentiation_(montgomery.to_montgomery(nb), pow, montgomery.one(), method, |a, b| montgomery.mul(a, b)));
+    }
+
+    let nb: T = nb % modulo.clone();
+    exponentiation_(nb, pow, T::one(), method, |a, b| mul_modulo(a, b, modulo.clone()))
+}
+
+/**
+ * Generic exponentiation with the multiplication mul and its neutral element one.
+ * !! the window size of method must have been checked !!
+ */
+fn exponentiation_<T, F>(nb: T, pow: T, one: T, method: Exponentiation, mul: F) -> T
+where
+    T: RsaInt,
+    F: Fn(T, T) -> T
+{
+    match method {
+        Exponentiation::Binary => binary_exponentiation_(nb, pow, one, mul),
+        Exponentiation::FixedWindow(k) => fixed_window_exponentiation_(nb, pow, one, k, mul),
+        Exponentiation::SlidingWindow(k) => sliding_window_exponentiation_(nb, pow, one, k, mul),
+        Exponentiation::Auto => match window_size(bit_length(pow.clone())) {
+            1 => binary_exponentiation_(nb, pow, one, mul),
+            k => sliding_window_exponentiation_(nb, pow, one, k, mul),
+        },
+    }
+}
+
+/**
+ * Returns the window size used by the Auto exponentiation
+ * for an exponent of the given number of bits.
+ */
+pub fn window_size(bits: usize) -> usize {
+    match bits {
+        0..=23 => 1,
+        24..=79 => 3,
+        80..=239 => 4,
+        240..=671 => 5,
+        _ => 6,
+    }
+}
+
+fn check_method_(method: Exponentiation) -> Result<(), RsaError> {
+    match method {
+        Exponentiation::FixedWindow(k) | Exponentiation::SlidingWindow(k) if k == 0 || k > 8 =>
+            Err(RsaError::Math(format!("window size {} is not between 1 and 8", k))),
+        _ => Ok(()),
+    }
+}
+
+fn binary_exponentiation_<T, F>(mut nb: T, mut pow: T, one: T, mul: F) -> T
+where
+    T: RsaInt,
+    F: Fn(T, T) -> T
+{
+    let mut result: T = one;
+
+    while pow > T::zero() {
+        if pow.clone() & T::one() > T::zero() {
+            result = mul(result, nb.clone());
+        }
+        pow = pow >> 1u8;
+        nb = mul(nb.clone(), nb);
+    }
+
+    result
+}
+
+fn fixed_window_exponentiation_<T, F>(nb: T, pow: T, one: T, k: usize, mul: F) -> T
+where
+    T: RsaInt,
+    F: Fn(T, T) -> T
+{
+    //table[i] = nb^i
+    let mut table: Vec<T> = Vec::with_capacity(1 << k);
+    table.push(one.clone());
+    for i in 1..(1 << k) {
+        table.push(mul(table[i - 1].clone(), nb.clone()));
+    }
+
+    let mask: T = (T::one() << k) - T::one();
+    let digits: usize = bit_length(pow.clone()).div_ceil(k);
+    let mut result: T = one;
+    for i in (0..digits).rev() {
+        for _ in 0..k {
+            result = mul(result.clone(), result);
+        }
+        let digit: usize = ((pow.clone() >> (i * k)) & mask.clone()).to_usize().unwrap();
+        if digit != 0 {
+            result = mul(result, table[digit].clone());
+        }
+    }
+
+    result
+}
+
+fn sliding_window_exponentiation_<T, F>(nb: T, pow: T, one: T, k: usize, mul: F) -> T
+where
+    T: RsaInt,
+    F: Fn(T, T) -> T
+{
+    //table[i] = nb^(2i + 1)
+    let nb2: T = mul(nb.clone(), nb.clone());
+    let mut table: Vec<T> = Vec::with_capacity(1 << (k - 1));
+    table.push(nb);
+    for i in 1..(1 << (k - 1)) {
+        table.push(mul(table[i - 1].clone(), nb2.clone()));
+    }
+
+    let bit = |i: usize| -> bool { (pow.clone() >> i) & T::one() == T::one() };
+    let mut result: T = one;
+    let mut i: usize = bit_length(pow.clone());
+    while i > 0 {
+        if !bit(i - 1) {
+            result = mul(result.clone(), result);
+            i -= 1;
+            continue;
+        }
+
+        //longest window ending with a 1 bit
+        let mut j: usize = i.saturating_sub(k);
+        while !bit(j) {
+            j += 1;
+        }
+        let width: usize = i - j;
+        let window: usize = ((pow.clone() >> j) & ((T::one() << width) - T::one())).to_usize().unwrap();
+        for _ in 0..width {
+            result = mul(result.clone(), result);
+        }
+        result = mul(result, table[window >> 1].clone());
+        i = j;
+    }
+
+    result
+}
+
+/**
+ * Checked modular exponentiation by square algorithm.
+ * Same as power_modulo but without the overflow-free fallback:
+ * the products are computed directly with checked multiplications.
+ *
+ * @return Some(nb^pow % modulo), None if a product overflowed T.
+ */
+pub fn checked_power_modulo<T>(mut nb: T, mut pow :T, modulo: T) -> Option<T>
+where T: RsaInt
+{
+    let mut result: T = T::one();
+    nb = nb % modulo.clone();
+
+    while pow > T::zero() {
+        if pow.clone() & T::one() > T::zero() {
+            result = result.checked_mul(&nb)? % modulo.clone();
+        }
+        pow = pow >> 1u8;
+        nb = nb.checked_mul(&nb)? % modulo.clone();
+    }
+
+    Some(result)
+}
+
+/**
+ * Overflow-free modular multiplication.
+ * The product is computed directly when it fits in T,
+ * otherwise it falls back on the double-and-add algorithm
+ * which never needs more than one bit over the modulo.
+ *
+ * @return a * b % modulo
+ */
+pub fn mul_modulo<T>(a: T, b: T, modulo: T) -> T
+where T: RsaInt
+{
+    if let Some(product) = a.checked_mul(&b) {
+        return product % modulo;
+    }
+
+    let mut a: T = a % modulo.clone();
+    let mut b: T = b % modulo.clone();
+    let mut result: T = T::zero();
+    while b > T::zero() {
+        if b.clone() & T::one() > T::zero() {
+            result = add_modulo(result, a.clone(), modulo.clone());
+        }
+        b = b >> 1u8;
+        a = add_modulo(a.clone(), a, modulo.clone());
+    }
+
+    result
+}
+
+/**
+ * Overflow-free modular addition.
+ * !! a and b must be lower than modulo !!
+ *
+ * @return (a + b) % modulo
+ */
+pub fn add_modulo<T>(a: T, b: T, modulo: T) -> T
+where T: RsaInt
+{
+    let gap: T = modulo - b.clone();   //a + b >= modulo <=> a >= modulo - b
+    if a >= gap {
+        a - gap
+    } else {
+        a + b
+    }
+}
+
+/**
+ * Montgomery arithmetic context for an odd modulo n.
+ * Numbers are kept in Montgomery form (a * R mod n, with R = 2^k > n)
+ * so that the modular reductions are made with masks and shifts instead of divisions.
+ */
+#[derive(Clone)]
+pub struct Montgomery<T>
+where T: RsaInt
+{
+    n: T,           //modulo
+    n_prime: T,     //-n^-1 mod R
+    r_bits: usize,  //k with R = 2^k
+    r_mask: T,      //R - 1
+    r2: T,          //R^2 mod n
+    one: T,         //R mod n, 1 in Montgomery form
+}
+
+impl<T> Montgomery<T>
+where T: RsaInt
+{
+    /**
+     * Precompute the Montgomery context of n.
+     * @return None if n is even or if the intermediates products could overflow T.
+     */
+    pub fn new(n: T) -> Option<Montgomery<T>> {
+        if n.clone() & T::one() == T::zero() {
+            return None;
+        }
+        n.checked_mul(&n)?;         //then R = 2^k can be represented
+
+        let r_bits: usize = bit_length(n.clone());
+        let r: T = T::one() << r_bits;
+        r.checked_mul(&r)?;
+        (r.clone() << 1u8).checked_mul(&n)?;
+
+        let r_mask: T = r.clone() - T::one();
+        let n_prime: T = (r.clone() - modular_inverse(n.clone() % r.clone(), r.clone()).ok()?) & r_mask.clone();
+        let one: T = r % n.clone();
+        let r2: T = mul_modulo(one.clone(), one.clone(), n.clone());
+
+        Some(Montgomery { n, n_prime, r_bits, r_mask, r2, one })
+    }
+
+    /**
+     * @return the modulus n.
+     */
+    pub fn modulo(&self) -> T {
+        self.n.clone()
+    }
+
+    /**
+     * @return 1 in Montgomery form (R mod n).
+     */
+    pub fn one(&self) -> T {
+        self.one.clone()
+    }
+
+    /**
+     * @return a in Montgomery form (a * R mod n).
+     */
+    pub fn to_montgomery(&self, a: T) -> T {
+        self.reduce((a % self.n.clone()) * self.r2.clone())
+    }
+
+    /**
+     * @return the number represented by a in Montgomery form (a * R^-1 mod n).
+     */
+    pub fn to_normal(&self, a: T) -> T {
+        self.reduce(a)
+    }
+
+    /**
+     * Montgomery product of two numbers in Montgomery form.
+     */
+    pub fn mul(&self, a: T, b: T) -> T {
+        self.reduce(a * b)
+    }
+
+    /**
+     * Modular exponentiation of a number in Montgomery form.
+     * @return nb^pow in Montgomery form.
+     */
+    pub fn pow(&self, nb: T, pow: T) -> T {
+        exponentiation_(nb, pow, self.one(), Exponentiation::Auto, |a, b| self.mul(a, b))
+    }
+
+    /**
+     * Modular exponentiation of a number in Montgomery form
+     * with the given exponentiation algorithm.
+     * @return nb^pow in Montgomery form, an error if the window size is not between 1 and 8.
+     */
+    pub fn pow_with(&self, nb: T, pow: T, method: Exponentiation) -> Result<T, RsaError> {
+        check_method_(method)?;
+        Ok(exponentiation_(nb, pow, self.one(), method, |a, b| self.mul(a, b)))
+    }
+
+    /**
+     * Modular exponentiation of a number in normal form.
+     * @return nb^pow % n
+     */
+    pub fn power_modulo(&self, nb: T, pow: T) -> T {
+        self.to_normal(self.pow(self.to_montgomery(nb), pow))
+    }
+
+    /**
+     * Modular exponentiation of a number in normal form
+     * with the given exponentiation algorithm.
+     * @return nb^pow % n, an error if the window size is not between 1 and 8.
+     */
+    pub fn power_modulo_with(&self, nb: T, pow: T, method: Exponentiation) -> Result<T, RsaError> {
+        Ok(self.to_normal(self.pow_with(self.to_montgomery(nb), pow, method)?))
+    }
+
+    /**
+     * Montgomery reduction (REDC).
+     * !! t must be lower than n * R !!
+     * @return t * R^-1 mod n
+     */
+    fn reduce(&self, t: T) -> T {
+        let m: T = ((t.clone() & self.r_mask.clone()) * self.n_prime.clone()) & self.r_mask.clone();
+        let u: T = (t + m * self.n.clone()) >> self.r_bits;
+        if u >= self.n {
+            u - self.n.clone()
+        } else {
+            u
+        }
+    }
+}
+
+/**
+ * Returns the number of significant bits of n.
+ */
+pub fn bit_length<T>(mut n: T) -> usize
+where T: RsaInt
+{
+    let mut bits: usize = 0;
+    while n > T::zero() {
+        n = n >> 1u8;
+        bits += 1;
+    }
+    bits
+}
+
+/**
+ * Returns the number of bytes needed to write n.
+ */
+pub fn byte_length<T>(n: T) -> usize
+where T: RsaInt
+{
+    bit_length(n).div_ceil(8)
+}
+
+/**
+ * Integer to octet string primitive (I2OSP).
+ * Write n in big-endian on exactly len bytes.
+ * @return None if n needs more than len bytes.
+ */
+pub fn to_bytes<T>(mut n: T, len: usize) -> Option<Vec<u8>>
+where T: RsaInt
+{
+    let mask: T = T::from(u8::MAX);
+    let mut bytes: Vec<u8> = vec![0; len];
+    for i in (0..len).rev() {
+        bytes[i] = (n.clone() & mask.clone()).to_u8()?;
+        n = n >> 8u8;
+    }
+    if n != T::zero() {
+        return None;
+    }
+    Some(bytes)
+}
+
+/**
+ * Octet string to integer primitive (OS2IP).
+ * Read bytes as a big-endian number.
+ * !! T must be big enough to contain all the bytes !!
+ */
+pub fn from_bytes<T>(bytes: &[u8]) -> T
+where T: RsaInt
+{
+    let mut n: T = T::zero();
+    for byte in bytes {
+        n = (n << 8u8) + T::from(*byte);
+    }
+    n
+}
+
+/**
+ * Write bytes as a lowercase hexadecimal string.
+ */
+pub fn to_hex(bytes: &[u8]) -> String {
+    let mut hex: String = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        hex.push_str(format!("{:02x}", byte).as_str());
+    }
+    hex
+}
+
+/**
+ * Read an hexadecimal string.
+ * @return None if s has an odd length or a non hexadecimal character.
+ */
+pub fn from_hex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) || !s.is_ascii() {
+        return None;
+    }
+    let mut bytes: Vec<u8> = Vec::with_capacity(s.len() / 2);
+    for i in (0..s.len()).step_by(2) {
+        bytes.push(u8::from_str_radix(&s[i..i + 2], 16).ok()?);
+    }
+    Some(bytes)
+}
+
+/**
+ * Extended Euclidean algorithm.
+ * Returns a tuple (r, u, v) where r is the greatest common divisor of a and b.
+ * If a and b are coprime then u and v are the multiplicative inverses of a and b.
+ * It can takes all primivites signed integers
+ */
+pub fn extended_euclidean_algorithm<T>(a: T, b: T) -> (T, T, T)
+where
+    T : num::PrimInt + num::Signed
+{
+    let mut r: T = a;
+    let mut u: T = T::one();
+    let mut v: T = T::zero();
+    let mut s: T = b;
+    let mut t: T = T::zero();
+    let mut p: T = T::one();
+
+    while s != T::zero() {
+        let  q: T = r / s;
+        let rs: T = r;
+        let us: T = u;
+        let vs: T = v;
+        r = s;
+        u = t;
+        v = p;
+        s = rs - q*s;
+        t = us - q*t;
+        p = vs - q*p;
+    }
+
+    (r, u, v)
+}
+
+/**
+ * Extended Euclidean algorithm.
+ * Returns a tuple (r, u, v) where r is the greatest common divisor of a and b.
+ * If a and b are coprime then u and v are the multiplicative inverses of a and b.
+ */
+pub fn extended_euclidean_algorithm_unsigned<T>(a: T, b: T) -> (T, T, T)
+where T: RsaInt
+{
+    let mut r0 = a.clone();
+    let mut s0 = T::one();
+    let mut t0 = T::zero();
+    let mut r1 = b.clone();
+    let mut s1 = T::zero();
+    let mut t1 = T::one();
+    let mut n = T::zero();
+    while r1 != T::zero() {
+        let q = r0.clone() / r1.clone();
+        let qr1 = q.clone() * r1.clone();
+        r0 = if r0 > qr1 { r0 - qr1 } else { qr1 - r0 };
+        std::mem::swap(&mut r0, &mut r1);
+        s0 = s0 + q.clone() * s1.clone();
+        std::mem::swap(&mut s0, &mut s1);
+        t0 = t0 + q * t1.clone();
+        std::mem::swap(&mut t0, &mut t1);
+        n = n + T::one();
+    }
+    // gcd = r0
+    if n & T::one() != T::zero() {
+        s0 = b - s0;
+    }
+    else {
+        t0 = a - t0;
+    }
+    (r0, s0, t0)
+}
+
+/**
+ * Returns the greatest common divisor of a and b.
+ * It implements the Euclidean algorithm.
+ * It can takes all primivites integers and big integers.
+ */
+pub fn gcd<T>(mut a: T, mut b: T) -> T
+where T: RsaInt
+{
+    while b != T::zero() {
+        let t: T = b.clone();
+        b = a % b;
+        a = t;
+    }
+
+    a
+}
+
+/**
+ * Returns the least common multiple of a and b.
+ * It can takes all primivites integers and big integers.
+ */
+pub fn lcm<T>(a: T, b: T) -> T
+where T: RsaInt
+{
+    if a == T::zero() || b == T::zero() {
+        return T::zero();
+    }
+    let r: T = gcd(a.clone(), b.clone());
+    (a / r) * b
+}
+
+/**
+ * Euler's totient of n = p * q with p and q two distinct primes.
+ * @return phi(n) = (p - 1)(q - 1)
+ */
+pub fn totient<T>(p: T, q: T) -> T
+where T: RsaInt
+{
+    (p - T::one()) * (q - T::one())
+}
+
+/**
+ * Carmichael function of n = p * q with p and q two distinct primes.
+ * It is the smallest m such that a^m = 1 mod n for all a coprime with n.
+ * @return lambda(n) = lcm(p - 1, q - 1)
+ */
+pub fn carmichael<T>(p: T, q: T) -> T
+where T: RsaInt
+{
+    lcm(p - T::one(), q - T::one())
+}
+
+/**
+ * Returns the modular inverse of a and b.
+ * @return an error if there is no modular inverse (a and b no coprime).
+ */
+pub fn modular_inverse<T>(a: T, b: T) -> Result<T, RsaError>
+where T: RsaInt
+{
+    let (r, _, v): (T, T, T) = extended_euclidean_algorithm_unsigned(b.clone(), a.clone());
+    if r != T::one() {
+        return Err(RsaError::Math(format!("no modular inverse of {} modulo {}", a, b)));
+    }
+    if v < T::zero() {          //correct v if the sign is negative
+        return Ok((v + b.clone()) % b); //custom modulo with negative sign
+    }                           //v mod b (with v negatif)
+    Ok(v)
+}
@@ -0,0 +1,215 @@
+use crate::hash::Hash;
+use rand::Rng;
+
+use crate::padding::{mgf1, PaddingError};
+use crate::rsa::{PrivateKey, PublicKey, RsaInt};
+use crate::rsa_tools::{bit_length, byte_length, from_bytes, to_bytes};
+
+//minimal number of 0xff bytes in a PKCS#1 v1.5 signature block
+const PKCS1_MIN_PADDING : usize = 8;
+
+/**
+ * RSASSA-PKCS1-v1_5 signature.
+ * The digest of the message is encoded in a block
+ * EM = 00 || 01 || FF..FF || 00 || DigestInfo then signed with the private exponent.
+ *
+ * @param message the bytes to sign.
+ * @param private_key the private key use to sign the message.
+ * @param hash the hash function used to digest the message.
+ *
+ * @return the signature on k bytes for a k bytes modulus.
+ */
+pub fn sign<T>(message: &[u8], private_key: &PrivateKey<T>, hash: Hash) -> Result<Vec<u8>, PaddingError>
+where T: RsaInt
+{
+    let k: usize = byte_length(private_key.n());
+    let block: Vec<u8> = emsa_pkcs1_encode_(message, k, hash)?;
+    let signature: T = private_key.decrypt(from_bytes(&block));
+    to_bytes(signature, k).ok_or(PaddingError::CipherTooBig)
+}
+
+/**
+ * RSASSA-PKCS1-v1_5 verification.
+ *
+ * @param message the signed bytes.
+ * @param signature the signature, it must have the size of the modulus.
+ * @param public_key the public key of the signer.
+ * @param hash the hash function used to digest the message.
+ *
+ * @return Ok if the signature is valid, Err(InvalidSignature) otherwise.
+ */
+pub fn verify<T>(message: &[u8], signature: &[u8], public_key: &PublicKey<T>, hash: Hash) -> Result<(), PaddingError>
+where T: RsaInt
+{
+    let k: usize = byte_length(public_key.n());
+    if signature.len() != k {
+        return Err(PaddingError::InvalidSignature);
+    }
+    let s: T = from_bytes(signature);
+    if s >= public_key.n() {
+        return Err(PaddingError::InvalidSignature);
+    }
+
+    let block: Vec<u8> = match to_bytes(public_key.encrypt(s), k) {
+        Some(block) => block,
+        None => return Err(PaddingError::InvalidSignature),
+    };
+    if block != emsa_pkcs1_encode_(message, k, hash)? {
+        return Err(PaddingError::InvalidSignature);
+    }
+    Ok(())
+}
+
+/**
+ * RSASSA-PSS signature (RFC 8017).
+ * A random salt is hashed with the message digest, so two signatures
+ * of the same message are different.
+ *
+ * @param message the bytes to sign.
+ * @param private_key the private key use to sign the message.
+ * @param hash the hash function used to digest the message.
+ * @param mgf_hash the hash function used by MGF1.
+ * @param salt_len the size of the salt in bytes, usually the digest size.
+ *
+ * @return the signature on k bytes for a k bytes modulus.
+ */
+pub fn pss_sign<T>(message: &[u8], private_key: &PrivateKey<T>, hash: Hash, mgf_hash: Hash, salt_len: usize) -> Result<Vec<u8>, PaddingError>
+where T: RsaInt
+{
+    let k: usize = byte_length(private_key.n());
+    let em_bits: usize = bit_length(private_key.n()).checked_sub(1).ok_or(PaddingError::KeyTooSmall)?;     //a zero modulus has no bit
+    let em_len: usize = em_bits.div_ceil(8);
+    let h_len: usize = hash.output_len();
+    if em_len < h_len + salt_len + 2 {
+        return Err(PaddingError::KeyTooSmall);
+    }
+
+    let mut salt: Vec<u8> = vec![0; salt_len];
+    rand::thread_rng().fill(&mut salt[..]);
+    let h: Vec<u8> = pss_hash_(message, &salt, hash);
+
+    //DB = PS || 01 || salt
+    let mut db: Vec<u8> = vec![0; em_len - salt_len - h_len - 2];
+    db.push(0x01);
+    db.extend_from_slice(&salt);
+    let mask: Vec<u8> = mgf1(&h, em_len - h_len - 1, mgf_hash);
+    for (byte, m) in db.iter_mut().zip(mask) {
+        *byte ^= m;
+    }
+    db[0] &= 0xff >> (8 * em_len - em_bits);
+
+    //EM = maskedDB || H || bc
+    let mut block: Vec<u8> = db;
+    block.extend_from_slice(&h);
+    block.push(0xbc);
+
+    let signature: T = private_key.decrypt(from_bytes(&block));
+    to_bytes(signature, k).ok_or(PaddingError::CipherTooBig)
+}
+
+/**
+ * RSASSA-PSS verification (RFC 8017).
+ *
+ * @param message the signed bytes.
+ * @param signature the signature, it must have the size of the modulus.
+ * @param public_key the public key of the signer.
+ * @param hash the hash function used to digest the message.
+ * @param mgf_hash the hash function used by MGF1.
+ * @param salt_len the size of the salt in bytes.
+ *
+ * @return Ok if the signature is valid, Err(InvalidSignature) otherwise.
+ */
+pub fn pss_verify<T>(message: &[u8], signature: &[u8], public_key: &PublicKey<T>, hash: Hash, mgf_hash: Hash, salt_len: usize) -> Result<(), PaddingError>
+where T: RsaInt
+{
+    let k: usize = byte_length(public_key.n());
+    let em_bits: usize = bit_length(public_key.n()).checked_sub(1).ok_or(PaddingError::KeyTooSmall)?;     //a zero modulus has no bit
+    let em_len: usize = em_bits.div_ceil(8);
+    let h_len: usize = hash.output_len();
+    if em_len < h_len + salt_len + 2 {
+        return Err(PaddingError::KeyTooSmall);
+    }
+    if signature.len() != k {
+        return Err(PaddingError::InvalidSignature);
+    }
+    let s: T = from_bytes(signature);
+    if s >= public_key.n() {
+        return Err(PaddingError::InvalidSignature);
+    }
+    let block: Vec<u8> = match to_bytes(public_key.encrypt(s), em_len) {
+        Some(block) => block,
+        None => return Err(PaddingError::InvalidSignature),
+    };
+
+    let top_mask: u8 = 0xff >> (8 * em_len - em_bits);
+    if block[em_len - 1] != 0xbc || block[0] & !top_mask != 0 {
+        return Err(PaddingError::InvalidSignature);
+    }
+    let h: &[u8] = &block[em_len - h_len - 1..em_len - 1];
+    let mut db: Vec<u8> = block[..em_len - h_len - 1].to_vec();
+    let mask: Vec<u8> = mgf1(h, em_len - h_len - 1, mgf_hash);
+    for (byte, m) in db.iter_mut().zip(mask) {
+        *byte ^= m;
+    }
+    db[0] &= top_mask;
+
+    let separator: usize = em_len - h_len - salt_len - 2;
+    if db[..separator].iter().any(|b| *b != 0x00) || db[separator] != 0x01 {
+        return Err(PaddingError::InvalidSignature);
+    }
+    if pss_hash_(message, &db[separator + 1..], hash) != h {
+        return Err(PaddingError::InvalidSignature);
+    }
+    Ok(())
+}
+
+/**
+ * @return H = Hash(00 00 00 00 00 00 00 00 || Hash(message) || salt)
+ */
+fn pss_hash_(message: &[u8], salt: &[u8], hash: Hash) -> Vec<u8> {
+    let mut prefixed: Vec<u8> = vec![0; 8];
+    prefixed.extend_from_slice(&hash.digest(message));
+    prefixed.extend_from_slice(salt);
+    hash.digest(&prefixed)
+}
+
+/**
+ * EMSA-PKCS1-v1_5 encoding of the message digest on k bytes.
+ */
+fn emsa_pkcs1_encode_(message: &[u8], k: usize, hash: Hash) -> Result<Vec<u8>, PaddingError> {
+    let mut digest_info: Vec<u8> = hash.digest_info_prefix().to_vec();
+    digest_info.extend_from_slice(&hash.digest(message));
+    if k < digest_info.len() + PKCS1_MIN_PADDING + 3 {
+        return Err(PaddingError::KeyTooSmall);
+    }
+
+    let mut block: Vec<u8> = Vec::with_capacity(k);
+    block.push(0x00);
+    block.push(0x01);
+    block.resize(k - digest_info.len() - 1, 0xff);
+    block.push(0x00);
+    block.extend_from_slice(&digest_info);
+    Ok(block)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rsa::{generate_bits, BigRsaInt};
+
+    #[test]
+    fn pss_signature_round_trip() {
+        let (public, private) = generate_bits::<BigRsaInt>(1024).unwrap();
+        let signature: Vec<u8> = pss_sign(b"hello", &private, Hash::Sha256, Hash::Sha256, 32).unwrap();
+        assert_eq!(pss_verify(b"hello", &signature, &public, Hash::Sha256, Hash::Sha256, 32), Ok(()));
+        assert_eq!(pss_verify(b"hellO", &signature, &public, Hash::Sha256, Hash::Sha256, 32), Err(PaddingError::InvalidSignature));
+    }
+
+    #[test]
+    fn pss_rejects_a_zero_modulus() {
+        let public: PublicKey<u128> = PublicKey::construct(65537, 0);
+        let private: PrivateKey<u128> = PrivateKey::construct(0, 65537, 1);
+        assert_eq!(pss_verify(b"hello", &[0], &public, Hash::Sha256, Hash::Sha256, 32), Err(PaddingError::KeyTooSmall));
+        assert_eq!(pss_sign(b"hello", &private, Hash::Sha256, Hash::Sha256, 32), Err(PaddingError::KeyTooSmall));
+    }
+}